@@ -0,0 +1,117 @@
+use crate::config::{EncodeRetryConfig, FfmpegConfig, OutputFormatConfig};
+use crate::ffmpeg;
+use crate::models::EncodeProgressReporter;
+use anyhow::Result;
+use std::path::Path;
+use std::time::Duration;
+use tracing::warn;
+
+/// Stderr substrings that mark a *hardware* encoder failure (driver limits,
+/// session caps, unsupported dimensions) rather than a transient one -
+/// matching one of these switches the retry to `libx264` instead of just
+/// re-running the same command again.
+const HARDWARE_FAILURE_MARKERS: &[&str] = &[
+    "No capable devices",
+    "OpenEncodeSessionEx failed",
+    "Cannot load",
+    "Invalid argument",
+];
+
+fn is_hardware_failure(message: &str) -> bool {
+    HARDWARE_FAILURE_MARKERS.iter().any(|marker| message.contains(marker))
+}
+
+/// Force `libx264`, bypassing `ffmpeg::detect_hardware_codec` entirely, for a
+/// software-fallback retry after a hardware-encoder failure.
+fn force_software_codec(config: &FfmpegConfig) -> FfmpegConfig {
+    let mut config = config.clone();
+    let mut advanced = config
+        .advanced
+        .clone()
+        .unwrap_or_else(crate::config::default_ffmpeg_advanced_config);
+    advanced.default_video_codec = "libx264".to_string();
+    config.advanced = Some(advanced);
+    config
+}
+
+/// Wrap `ffmpeg::generate_clip` in a retry loop so a flaky hardware encoder
+/// doesn't fail a whole clip outright.
+///
+/// A hardware-encoder failure (matched via `is_hardware_failure` against the
+/// captured stderr) falls back once to `libx264` and retries immediately,
+/// without spending one of the backoff attempts. Any other failure is
+/// retried with exponential backoff, up to `retry.max_tries` attempts in
+/// total. Once
+/// tries are exhausted, returns an error carrying the last captured stderr
+/// and command line (surfaced by `ffmpeg::generate_clip` itself) for
+/// diagnosis.
+pub async fn generate_clip_with_retry(
+    input_path: &Path,
+    output_path: &Path,
+    start_time: f64,
+    duration: f64,
+    ffmpeg_config: &FfmpegConfig,
+    concurrent_clips: usize,
+    output_format: &OutputFormatConfig,
+    thread_policy: &str,
+    resolution: Option<(u32, u32)>,
+    max_height: Option<u32>,
+    retry: &EncodeRetryConfig,
+    progress: Option<&EncodeProgressReporter>,
+) -> Result<()> {
+    let mut attempt_config = ffmpeg_config.clone();
+    let mut used_software_fallback = false;
+    let mut backoff = Duration::from_millis(retry.initial_backoff_ms);
+    let max_tries = retry.max_tries.max(1);
+    let mut attempt = 1;
+
+    loop {
+        let result = ffmpeg::generate_clip(
+            input_path,
+            output_path,
+            start_time,
+            duration,
+            &attempt_config,
+            concurrent_clips,
+            output_format,
+            thread_policy,
+            resolution,
+            max_height,
+            progress,
+        )
+        .await;
+
+        let err = match result {
+            Ok(()) => return Ok(()),
+            Err(e) => e,
+        };
+        let message = err.to_string();
+
+        if !used_software_fallback && is_hardware_failure(&message) {
+            warn!(
+                "[broker] Hardware encoder failure on attempt {}/{}, falling back to libx264: {}",
+                attempt, max_tries, message
+            );
+            attempt_config = force_software_codec(&attempt_config);
+            used_software_fallback = true;
+            // The fallback itself doesn't count against max_tries - it's a
+            // codec switch, not a retry of the same doomed command.
+            continue;
+        }
+
+        if attempt >= max_tries {
+            return Err(err.context(format!(
+                "ffmpeg encode failed after {} attempt(s)",
+                attempt
+            )));
+        }
+
+        warn!(
+            "[broker] Transient encode failure on attempt {}/{}, retrying in {:?}: {}",
+            attempt, max_tries, backoff, message
+        );
+        tokio::time::sleep(backoff).await;
+        backoff *= 2;
+        attempt += 1;
+    }
+}
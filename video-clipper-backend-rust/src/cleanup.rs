@@ -1,12 +1,15 @@
-use crate::config::Config;
-use std::path::PathBuf;
+use crate::models::AppState;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::time::{Duration, SystemTime};
 use tokio::fs;
 use tracing::{error, info, warn};
 
-/// Clean up old clips and uploads older than the specified duration
-pub async fn cleanup_old_files(config: &Config, max_age: Duration) -> anyhow::Result<()> {
+/// Clean up old clips and uploads older than the specified duration, then
+/// enforce the total disk budget by evicting whole clip sets if still over.
+pub async fn cleanup_old_files(state: &Arc<AppState>, max_age: Duration) -> anyhow::Result<()> {
+    let config = &state.config;
     let now = SystemTime::now();
     let mut total_deleted = 0;
     let mut total_size_freed = 0u64;
@@ -21,6 +24,12 @@ pub async fn cleanup_old_files(config: &Config, max_age: Duration) -> anyhow::Re
         error!("[cleanup] Error cleaning uploads directory: {}", e);
     }
 
+    // Age-based deletion alone can't bound disk usage during an upload burst -
+    // enforce a hard size budget by evicting whole clip sets until under it
+    if let Err(e) = enforce_size_budget(state, &mut total_deleted, &mut total_size_freed).await {
+        error!("[cleanup] Error enforcing disk size budget: {}", e);
+    }
+
     if total_deleted > 0 {
         let size_mb = total_size_freed as f64 / 1024.0 / 1024.0;
         info!(
@@ -45,10 +54,10 @@ async fn cleanup_directory(
     }
 
     let mut entries = fs::read_dir(dir).await?;
-    
+
     while let Some(entry) = entries.next_entry().await? {
         let path = entry.path();
-        
+
         // Get metadata
         let metadata = match fs::metadata(&path).await {
             Ok(m) => m,
@@ -128,7 +137,7 @@ async fn cleanup_directory(
 async fn calculate_dir_size(dir: &PathBuf) -> anyhow::Result<u64> {
     let mut total_size = 0u64;
     let mut dirs_to_process = vec![dir.clone()];
-    
+
     while let Some(current_dir) = dirs_to_process.pop() {
         let mut entries = match fs::read_dir(&current_dir).await {
             Ok(entries) => entries,
@@ -137,7 +146,7 @@ async fn calculate_dir_size(dir: &PathBuf) -> anyhow::Result<u64> {
                 continue;
             }
         };
-        
+
         while let Some(entry) = entries.next_entry().await? {
             let path = entry.path();
             let metadata = match fs::metadata(&path).await {
@@ -147,7 +156,7 @@ async fn calculate_dir_size(dir: &PathBuf) -> anyhow::Result<u64> {
                     continue;
                 }
             };
-            
+
             if metadata.is_dir() {
                 dirs_to_process.push(path);
             } else {
@@ -155,32 +164,224 @@ async fn calculate_dir_size(dir: &PathBuf) -> anyhow::Result<u64> {
             }
         }
     }
-    
+
     Ok(total_size)
 }
 
-/// Start a background task that periodically cleans up old files
-pub fn start_cleanup_task(config: Arc<Config>) -> tokio::task::JoinHandle<()> {
-    let max_age = Duration::from_secs(config.limits.cleanup_max_age_seconds);
-    let cleanup_interval = Duration::from_secs(config.limits.cleanup_interval_seconds);
-    
+/// Recover the `video_id` a top-level `output_dir`/`upload_dir` entry belongs
+/// to. `output_dir` entries are directories named exactly `{video_id}` (plus
+/// `{video_id}-preview.mp4` eager-preview files); `upload_dir` entries are
+/// files named `{video_id}-{original_name}`, and
+/// since `video_id` itself is never dash-containing (a unix-seconds prefix
+/// plus a dash-stripped UUID), splitting on the first dash recovers it cleanly.
+fn video_id_for_entry(path: &Path, is_upload: bool) -> Option<String> {
+    let file_name = path.file_name()?.to_str()?;
+    if is_upload {
+        file_name.split_once('-').map(|(id, _)| id.to_string())
+    } else {
+        Some(file_name.split_once('-').map_or(file_name, |(id, _)| id).to_string())
+    }
+}
+
+/// One video's combined footprint across `output_dir` and `upload_dir`,
+/// evicted as a single unit so a budget sweep never deletes half a clip set.
+struct ClipSet {
+    paths: Vec<PathBuf>,
+    size: u64,
+    // Most recent mtime across the set's files, used as the "oldest first"
+    // eviction key when nothing in `AppState.last_served` overrides it
+    last_activity: SystemTime,
+}
+
+/// Evict whole clip sets from the clips/uploads directories, oldest (or
+/// least-recently-served, per `config.retention.eviction_policy`) first,
+/// until their combined size is back under `config.limits.max_total_bytes`.
+/// Clip sets with a `video_id` present in `state.in_progress` (marked by
+/// `clip_handler` for the length of a /clip request) are skipped, so clip
+/// generation or delivery is never evicted out from under it. An upload that
+/// is still being written is not marked there and has no such protection.
+async fn enforce_size_budget(
+    state: &Arc<AppState>,
+    total_deleted: &mut usize,
+    total_size_freed: &mut u64,
+) -> anyhow::Result<()> {
+    let config = &state.config;
+    let budget = config.limits.max_total_bytes;
+
+    let mut output_entries = Vec::new();
+    collect_entries(&config.output_dir, &mut output_entries).await?;
+    let mut upload_entries = Vec::new();
+    collect_entries(&config.upload_dir, &mut upload_entries).await?;
+
+    let current_total: u64 = output_entries.iter().chain(upload_entries.iter())
+        .map(|(_, _, size)| size)
+        .sum();
+    if current_total <= budget {
+        return Ok(());
+    }
+
+    let mut sets: HashMap<String, ClipSet> = HashMap::new();
+    for (path, modified, size) in output_entries {
+        let Some(video_id) = video_id_for_entry(&path, false) else { continue };
+        let set = sets.entry(video_id).or_insert_with(|| ClipSet {
+            paths: Vec::new(),
+            size: 0,
+            last_activity: SystemTime::UNIX_EPOCH,
+        });
+        set.paths.push(path);
+        set.size += size;
+        set.last_activity = set.last_activity.max(modified);
+    }
+    for (path, modified, size) in upload_entries {
+        let Some(video_id) = video_id_for_entry(&path, true) else { continue };
+        let set = sets.entry(video_id).or_insert_with(|| ClipSet {
+            paths: Vec::new(),
+            size: 0,
+            last_activity: SystemTime::UNIX_EPOCH,
+        });
+        set.paths.push(path);
+        set.size += size;
+        set.last_activity = set.last_activity.max(modified);
+    }
+
+    let in_progress = state.in_progress.read().await;
+    let mut candidates: Vec<(String, ClipSet)> = sets
+        .into_iter()
+        .filter(|(video_id, _)| !in_progress.contains(video_id))
+        .collect();
+    drop(in_progress);
+
+    info!(
+        "[cleanup] ⚠️  Disk usage {:.2} MB exceeds budget {:.2} MB, evicting clip sets ({})",
+        current_total as f64 / 1024.0 / 1024.0,
+        budget as f64 / 1024.0 / 1024.0,
+        config.retention.eviction_policy
+    );
+
+    if config.retention.eviction_policy == "least_recently_served" {
+        let last_served = state.last_served.read().await;
+        candidates.sort_by_key(|(video_id, set)| {
+            last_served.get(video_id).copied().unwrap_or(set.last_activity)
+        });
+    } else {
+        candidates.sort_by_key(|(_, set)| set.last_activity);
+    }
+
+    let mut remaining = current_total;
+    for (video_id, set) in candidates {
+        if remaining <= budget {
+            break;
+        }
+
+        let mut evicted_any = false;
+        for path in &set.paths {
+            let is_dir = fs::metadata(path).await.map(|m| m.is_dir()).unwrap_or(false);
+            let result = if is_dir {
+                fs::remove_dir_all(path).await
+            } else {
+                fs::remove_file(path).await
+            };
+
+            match result {
+                Ok(()) => evicted_any = true,
+                Err(e) => error!("[cleanup] ❌ Failed to evict {:?}: {}", path, e),
+            }
+        }
+
+        if evicted_any {
+            remaining = remaining.saturating_sub(set.size);
+            *total_deleted += 1;
+            *total_size_freed += set.size;
+            info!(
+                "[cleanup] ✅ Evicted clip set {} over budget ({:.2} MB reclaimed)",
+                video_id,
+                set.size as f64 / 1024.0 / 1024.0
+            );
+
+            // Keep the in-memory caches and metadata store in sync with disk so a
+            // later lookup doesn't resurrect a video whose files are already gone
+            state.videos.write().await.remove(&video_id);
+            // clip_cache is keyed by "{video_id}:{ClipRequest::request_shape_hash()}",
+            // not the bare video_id, since a video can have multiple cached
+            // request shapes - evict all of them.
+            let cache_key_prefix = format!("{}:", video_id);
+            state
+                .clip_cache
+                .write()
+                .await
+                .retain(|key, _| !key.starts_with(&cache_key_prefix));
+            state.last_served.write().await.remove(&video_id);
+            if let Err(e) = state.db.delete_video(&video_id).await {
+                error!(
+                    "[cleanup] ❌ Failed to remove evicted video {} from metadata store: {}",
+                    video_id, e
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Collect (path, modified, size) tuples for every top-level entry in `dir`
+async fn collect_entries(dir: &PathBuf, out: &mut Vec<(PathBuf, SystemTime, u64)>) -> anyhow::Result<()> {
+    if !dir.exists() {
+        return Ok(());
+    }
+
+    let mut entries = fs::read_dir(dir).await?;
+    while let Some(entry) = entries.next_entry().await? {
+        let path = entry.path();
+        let metadata = match fs::metadata(&path).await {
+            Ok(m) => m,
+            Err(e) => {
+                warn!("[cleanup] Failed to get metadata for {:?}: {}", path, e);
+                continue;
+            }
+        };
+
+        let modified = match metadata.modified() {
+            Ok(m) => m,
+            Err(e) => {
+                warn!("[cleanup] Failed to get modification time for {:?}: {}", path, e);
+                continue;
+            }
+        };
+
+        let size = if metadata.is_dir() {
+            calculate_dir_size(&path).await.unwrap_or(0)
+        } else {
+            metadata.len()
+        };
+
+        out.push((path, modified, size));
+    }
+
+    Ok(())
+}
+
+/// Start a background task that periodically cleans up old files and
+/// enforces the disk size budget
+pub fn start_cleanup_task(state: Arc<AppState>) -> tokio::task::JoinHandle<()> {
+    let max_age = Duration::from_secs(state.config.limits.cleanup_max_age_seconds);
+    let cleanup_interval = Duration::from_secs(state.config.limits.cleanup_interval_seconds);
+
     tokio::spawn(async move {
         let mut interval = tokio::time::interval(cleanup_interval);
         interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
-        
+
         info!(
             "[cleanup] ðŸ§¹ Starting periodic cleanup task (interval: {:.1} min, max age: {:.1} min)",
             cleanup_interval.as_secs_f64() / 60.0,
             max_age.as_secs_f64() / 60.0
         );
-        
+
         loop {
             interval.tick().await;
-            
-            if let Err(e) = cleanup_old_files(&config, max_age).await {
+
+            if let Err(e) = cleanup_old_files(&state, max_age).await {
                 error!("[cleanup] Periodic cleanup error: {}", e);
             }
         }
     })
 }
-
@@ -0,0 +1,54 @@
+use clap::Parser;
+
+/// Command-line overrides for the video clipper backend. Precedence is
+/// CLI > env vars > config.toml > built-in defaults - any flag left unset
+/// here falls through to whatever `Config::load` already resolved.
+#[derive(Parser, Debug, Clone)]
+#[command(name = "video-clipper-backend", about = "TikTok-style video clipper backend")]
+pub struct CliArgs {
+    /// Port to listen on (overrides PORT env var / config.toml server.port)
+    #[arg(long)]
+    pub port: Option<u16>,
+
+    /// Directory to store uploaded videos (overrides UPLOAD_DIR / server.upload_dir)
+    #[arg(long)]
+    pub upload_dir: Option<String>,
+
+    /// Directory to store generated clips (overrides OUTPUT_DIR / server.output_dir)
+    #[arg(long)]
+    pub output_dir: Option<String>,
+
+    /// Maximum accepted upload size in bytes (overrides MAX_FILE_SIZE / server.max_file_size)
+    #[arg(long)]
+    pub max_file_size: Option<u64>,
+
+    /// Maximum number of clips encoded concurrently (overrides MAX_CONCURRENT_CLIPS)
+    #[arg(long)]
+    pub max_concurrent_clips: Option<usize>,
+
+    /// x264/SVT-AV1 CRF value (lower = higher quality)
+    #[arg(long)]
+    pub crf: Option<u8>,
+
+    /// Encoder preset (e.g. medium, fast, veryfast)
+    #[arg(long)]
+    pub preset: Option<String>,
+
+    /// Video codec to encode with (e.g. libx264, libsvtav1, librav1e)
+    #[arg(long)]
+    pub codec: Option<String>,
+
+    /// Enable target-quality mode and set the VMAF score to aim for
+    #[arg(long)]
+    pub target_vmaf: Option<f64>,
+
+    /// Print the fully-resolved configuration as TOML and exit, without starting the server
+    #[arg(long)]
+    pub print_config: bool,
+}
+
+impl CliArgs {
+    pub fn parse_args() -> Self {
+        CliArgs::parse()
+    }
+}
@@ -7,6 +7,12 @@ pub struct PerformanceConfig {
     pub max_concurrent_clips: Option<usize>, // None = auto-detect
     pub upload_buffer_size: usize,
     pub upload_log_interval: usize,
+    /// How ffmpeg `-threads` are allocated across concurrently running clips:
+    /// `"static_buckets"` uses the coarse 1-2/3-4/many thresholds below, while
+    /// `"dynamic_split"` divides detected parallelism by the actual number of
+    /// clips running right now, which tracks container CPU quotas more closely.
+    #[serde(default = "default_thread_policy")]
+    pub thread_policy: String,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -51,6 +57,10 @@ pub struct FfmpegAdvancedConfig {
     pub threads_when_many_clips_min: usize,
     #[serde(default = "default_threads_many_max")]
     pub threads_when_many_clips_max: usize,
+    #[serde(default = "default_av1_preset")]
+    pub av1_preset: u8, // SVT-AV1 -preset: 0 (slowest/best) .. 13 (fastest)
+    #[serde(default = "default_av1_speed")]
+    pub av1_speed: u8, // rav1e --speed: 0 (slowest/best) .. 10 (fastest)
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -66,8 +76,120 @@ pub struct FfmpegConfig {
     pub audio_codec: String,
     pub use_input_seeking: bool,
     pub additional_flags: Vec<String>,
+    /// Relocate the moov atom ahead of mdat (`-movflags +faststart`) so
+    /// players/browsers can start playback before the whole clip has
+    /// downloaded. Ignored for fragmented/HLS output, which is already
+    /// progressively playable via `frag_keyframe+empty_moov`.
+    #[serde(default = "default_faststart")]
+    pub faststart: bool,
     #[serde(default)]
     pub advanced: Option<FfmpegAdvancedConfig>,
+    #[serde(default)]
+    pub target_quality: Option<TargetQualityConfig>,
+    #[serde(default)]
+    pub adaptive_quality: Option<AdaptiveQualityConfig>,
+    #[serde(default)]
+    pub audio: Option<AudioConfig>,
+    #[serde(default)]
+    pub retry: Option<EncodeRetryConfig>,
+    #[serde(default)]
+    pub hdr: Option<HdrConfig>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct AudioConfig {
+    /// Isolate a single source channel (0-indexed) into mono output, e.g. to
+    /// pull a clean lavalier mic off one channel of a stereo recording.
+    #[serde(default)]
+    pub channel_map: Option<u8>,
+    /// Re-encode audio with this codec instead of stream-copying. Setting
+    /// channel_map or normalize also forces a re-encode (those need a
+    /// filter), with this codec or `aac` when it is unset.
+    #[serde(default)]
+    pub codec: Option<String>,
+    #[serde(default = "default_audio_bitrate")]
+    pub bitrate: String,
+    #[serde(default)]
+    pub normalize: bool,
+    #[serde(default = "default_target_lufs")]
+    pub target_lufs: f64,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TargetQualityConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_target_vmaf")]
+    pub target_vmaf: f64,
+    #[serde(default = "default_min_crf")]
+    pub min_crf: u8,
+    #[serde(default = "default_max_crf")]
+    pub max_crf: u8,
+    #[serde(default = "default_probe_duration")]
+    pub probe_duration: f64,
+    #[serde(default = "default_probes")]
+    pub probes: usize,
+}
+
+/// Governs `vmaf::solve_crf_adaptive`, a second VMAF-targeting knob alongside
+/// `TargetQualityConfig` above. Where that one probes a fixed-duration segment
+/// and caches nothing across calls, this one encodes a fixed number of frames
+/// per probe (cheaper per-sample) and memoizes each CRF it tries for the
+/// duration of a single clip's search, so a bracket that revisits a CRF never
+/// re-encodes it.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct AdaptiveQualityConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_adaptive_vmaf")]
+    pub vmaf: f64,
+    #[serde(default = "default_adaptive_crf_min")]
+    pub crf_min: u8,
+    #[serde(default = "default_adaptive_crf_max")]
+    pub crf_max: u8,
+    /// Stop the search once a probe's VMAF lands within this many points of
+    /// `vmaf`.
+    #[serde(default = "default_adaptive_tolerance")]
+    pub tolerance: f64,
+    /// Frames encoded per probe (not a duration) - keeps probes cheap
+    /// regardless of the clip's own length.
+    #[serde(default = "default_adaptive_probe_frames")]
+    pub probe_frames: usize,
+}
+
+/// Governs `broker::generate_clip_with_retry`, which wraps a single
+/// `ffmpeg::generate_clip` call in a retry loop so a flaky hardware encoder
+/// (driver limits, session caps, unsupported dimensions) doesn't fail a
+/// whole clip outright.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct EncodeRetryConfig {
+    #[serde(default = "default_retry_enabled")]
+    pub enabled: bool,
+    #[serde(default = "default_retry_max_tries")]
+    pub max_tries: u32,
+    #[serde(default = "default_retry_initial_backoff_ms")]
+    pub initial_backoff_ms: u64,
+}
+
+/// Governs HDR-aware handling of `color_transfer`/`color_primaries` detected
+/// via `ffmpeg::probe_color_info` (PQ/`smpte2084`, HLG/`arib-std-b67`,
+/// `bt2020` primaries). The user's configured `mode` always wins over what
+/// the source actually is - an SDR source with `mode = "preserve"` just
+/// encodes as SDR (there's nothing to preserve), and an HDR source with
+/// `mode = "tonemap_sdr"` is tone-mapped down regardless of how wide its
+/// original gamut was.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct HdrConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// `"tonemap_sdr"` filters an HDR source down to 8-bit bt709 SDR with
+    /// `zscale`+`tonemap`; `"preserve"` instead passes the detected
+    /// transfer/primaries/space straight through (plus fixed bt2020/PQ colour
+    /// tags via `-x264-params` for libx264/libx265) so the output stays HDR.
+    /// The pixel format stays `ffmpeg.pixel_format` in both modes; preserve
+    /// does not switch the encode to 10-bit.
+    #[serde(default = "default_hdr_mode")]
+    pub mode: String,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -107,6 +229,183 @@ pub struct LimitsConfig {
     pub cleanup_interval_seconds: u64,
     #[serde(default = "default_cleanup_max_age")]
     pub cleanup_max_age_seconds: u64,
+    #[serde(default = "default_max_total_bytes")]
+    pub max_total_bytes: u64,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DiagnosticsConfig {
+    #[serde(default)]
+    pub enable_state_dump: bool,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SceneDetectionConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_scene_method")]
+    pub method: String, // "standard" (64x64 analysis grid, 2fps) | "fast" (32x32, 1fps)
+    #[serde(default = "default_min_scene_len")]
+    pub min_scene_len: f64,
+    // Overrides the analysis grid's side length (see `method`) when set
+    #[serde(default)]
+    pub downscale_height: Option<u32>,
+    /// Floor for the per-frame feature-diff cut threshold, so a mostly-static
+    /// video (tiny rolling stddev) doesn't flag noise as a cut. The adaptive
+    /// threshold actually used is `max(threshold, mean + sensitivity * stddev)`
+    /// over a sliding window of recent frame-to-frame diffs.
+    #[serde(default = "default_scene_threshold")]
+    pub threshold: f64,
+    /// `k` in the adaptive cut threshold `mean + k * stddev`: how many standard
+    /// deviations above the recent-history average a frame diff must clear to
+    /// be flagged as a scene change. Lower = more cuts detected (more sensitive).
+    #[serde(default = "default_scene_sensitivity")]
+    pub sensitivity: f64,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct OutputFormatConfig {
+    #[serde(default = "default_container")]
+    pub container: String, // "mp4" | "fmp4" | "hls"
+    #[serde(default = "default_segment_duration")]
+    pub segment_duration: f64,
+    #[serde(default = "default_playlist_name")]
+    pub playlist_name: String,
+}
+
+/// One named rendition preset for multi-codec/multi-resolution clip output
+/// (e.g. `h264_1080p`, `hevc_1080p`, `av1_720p`). `ClipRequest.output_profiles`
+/// references these by `name`; `crf`/`max_height` that are `None` fall back to
+/// the request's base `ffmpeg.crf` / source resolution unchanged.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct OutputProfile {
+    pub name: String,
+    pub codec: String,
+    #[serde(default)]
+    pub crf: Option<u8>,
+    #[serde(default)]
+    pub max_height: Option<u32>,
+    #[serde(default = "default_container")]
+    pub container: String,
+}
+
+/// Bot-API credentials and message formatting for the Telegram `ClipSink`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TelegramSinkConfig {
+    pub bot_token: String,
+    pub chat_id: String,
+    /// `{video_id}`/`{clip_id}` are substituted in before sending
+    #[serde(default = "default_telegram_caption_template")]
+    pub caption_template: String,
+}
+
+/// Optional delivery of finished clips straight to a chat/channel once
+/// `generateClips` succeeds, instead of operators pulling files off the server.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PublishConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default)]
+    pub telegram: Option<TelegramSinkConfig>,
+    #[serde(default = "default_publish_max_concurrent_uploads")]
+    pub max_concurrent_uploads: usize,
+    /// Minimum spacing between uploads, on top of the concurrency cap, so a
+    /// burst of short clips doesn't trip the sink's flood limits
+    #[serde(default = "default_publish_min_interval_ms")]
+    pub min_interval_ms: u64,
+}
+
+/// Governs how the periodic cleanup task picks which clip sets to evict once
+/// disk usage is over `limits.max_total_bytes`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RetentionConfig {
+    /// `"oldest_first"` evicts by mtime of the clip set's files; `"least_recently_served"`
+    /// evicts by `AppState.last_served`, falling back to mtime for a clip set that
+    /// was never served.
+    #[serde(default = "default_eviction_policy")]
+    pub eviction_policy: String,
+}
+
+/// Governs `ffmpeg::detect_scene_cuts`/`ffmpeg::snap_clip_bounds`, which snap a
+/// single clip's start/end to real shot boundaries (via ffmpeg's built-in
+/// `scene` filter) instead of the from-scratch raw-pixel-diff analysis
+/// `scene_detection` above uses for batch clip planning.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SceneSnapConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// `scene` filter score (0.0-1.0) a frame transition must clear to count
+    /// as a cut; higher = fewer, harder cuts detected.
+    #[serde(default = "default_scene_snap_threshold")]
+    pub threshold: f64,
+    /// Cuts closer together than this are deduped to the earlier one, and a
+    /// snapped segment is never shrunk below it.
+    #[serde(default = "default_scene_snap_min_segment_len")]
+    pub min_segment_len: f64,
+    /// A snapped segment is never extended past this length even if the next
+    /// cut is further out.
+    #[serde(default = "default_scene_snap_max_segment_len")]
+    pub max_segment_len: f64,
+}
+
+/// Governs the pipe-based eager-preview ingestion path in `upload_handler`,
+/// which feeds the incoming multipart bytes straight into an ffmpeg `-i
+/// pipe:0` process (see `ffmpeg::generate_clip_from_stdin`) concurrently with
+/// writing them to `upload_dir`, so a short preview clip is ready by the time
+/// the upload finishes instead of requiring a separate re-read of the file
+/// afterward. The file write itself is never skipped - later `/clip` calls
+/// for arbitrary segments still need the source on disk - so this only saves
+/// the first clip's worth of re-reading, not the upload's disk I/O overall.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct StreamingIngestConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// Length of the eager preview clip pulled off the upload stream,
+    /// independent of `limits.default_max_clip_length`.
+    #[serde(default = "default_streaming_ingest_preview_length")]
+    pub preview_clip_length: f64,
+    /// Caps how many uploads can run the pipe path at once - each one holds
+    /// an extra ffmpeg child alongside the multipart stream for the whole
+    /// upload, so this is deliberately separate from `max_concurrent_clips`.
+    #[serde(default = "default_streaming_ingest_max_concurrent")]
+    pub max_concurrent: usize,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct AdmissionConfig {
+    #[serde(default = "default_min_free_memory_gb")]
+    pub min_free_memory_gb: f64,
+    #[serde(default = "default_max_process_rss_mb")]
+    pub max_process_rss_mb: f64,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PrivacyConfig {
+    #[serde(default = "default_strip_metadata")]
+    pub strip_metadata: bool,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DedupConfig {
+    #[serde(default = "default_dedup_enabled")]
+    pub enabled: bool,
+    #[serde(default = "default_dedup_tolerance_bits_per_frame")]
+    pub tolerance_bits_per_frame: u32,
+    /// Max Hamming distance (out of 64 bits) for two generated clips' spatial-
+    /// temporal hashes to be considered the same shot, gating the same
+    /// `enabled` flag as upload-time dedup above.
+    #[serde(default = "default_clip_dedup_tolerance_bits")]
+    pub clip_dedup_tolerance_bits: u32,
+}
+
+/// Real codecs/containers (as reported by ffprobe) an upload is allowed to
+/// have, independent of what the client's multipart `Content-Type` claims.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ValidationConfig {
+    #[serde(default = "default_allowed_video_codecs")]
+    pub allowed_video_codecs: Vec<String>,
+    #[serde(default = "default_allowed_containers")]
+    pub allowed_containers: Vec<String>,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -116,9 +415,99 @@ pub struct ConfigFile {
     pub ffmpeg: FfmpegConfig,
     pub optimization: OptimizationConfig,
     pub limits: LimitsConfig,
+    #[serde(default)]
+    pub dedup: Option<DedupConfig>,
+    #[serde(default)]
+    pub privacy: Option<PrivacyConfig>,
+    #[serde(default)]
+    pub diagnostics: Option<DiagnosticsConfig>,
+    #[serde(default)]
+    pub admission: Option<AdmissionConfig>,
+    #[serde(default)]
+    pub scene_detection: Option<SceneDetectionConfig>,
+    #[serde(default)]
+    pub output_format: Option<OutputFormatConfig>,
+    #[serde(default)]
+    pub validation: Option<ValidationConfig>,
+    #[serde(default)]
+    pub output_profiles: Vec<OutputProfile>,
+    #[serde(default)]
+    pub publish: Option<PublishConfig>,
+    #[serde(default)]
+    pub retention: Option<RetentionConfig>,
+    #[serde(default)]
+    pub scene_snap: Option<SceneSnapConfig>,
+    #[serde(default)]
+    pub streaming_ingest: Option<StreamingIngestConfig>,
 }
 
 // Default functions for serde
+fn default_thread_policy() -> String { "static_buckets".to_string() }
+fn default_faststart() -> bool { true }
+fn default_allowed_video_codecs() -> Vec<String> {
+    vec!["h264".to_string(), "hevc".to_string(), "vp9".to_string(), "av1".to_string()]
+}
+fn default_allowed_containers() -> Vec<String> {
+    vec!["mov,mp4,m4a,3gp,3g2,mj2".to_string(), "matroska,webm".to_string(), "avi".to_string()]
+}
+/// Catalog of `OutputProfile` presets available to `ClipRequest.output_profiles`.
+/// Empty doesn't mean "no renditions" - an empty `output_profiles` request still
+/// gets the single default rendition from `config.ffmpeg`; this list only
+/// matters once a caller actually names one of these presets.
+fn default_output_profiles() -> Vec<OutputProfile> {
+    vec![
+        OutputProfile {
+            name: "h264_1080p".to_string(),
+            codec: "libx264".to_string(),
+            crf: None,
+            max_height: Some(1080),
+            container: default_container(),
+        },
+        OutputProfile {
+            name: "hevc_1080p".to_string(),
+            codec: "libx265".to_string(),
+            crf: None,
+            max_height: Some(1080),
+            container: default_container(),
+        },
+        OutputProfile {
+            name: "av1_720p".to_string(),
+            codec: "libsvtav1".to_string(),
+            crf: None,
+            max_height: Some(720),
+            container: default_container(),
+        },
+    ]
+}
+/// Fully-populated `FfmpegAdvancedConfig` built from defaults, used wherever an
+/// optional `advanced` block needs to exist just so one field can be overridden
+/// (a CLI `--codec` flag, or an output profile's `codec`).
+pub fn default_ffmpeg_advanced_config() -> FfmpegAdvancedConfig {
+    FfmpegAdvancedConfig {
+        thread_queue_size: default_thread_queue_size(),
+        bufsize: default_bufsize(),
+        maxrate: default_maxrate(),
+        gop_size: default_gop_size(),
+        keyint_min: default_keyint_min(),
+        default_video_codec: default_video_codec(),
+        videotoolbox_quality_min: default_videotoolbox_quality_min(),
+        videotoolbox_quality_max: default_videotoolbox_quality_max(),
+        videotoolbox_crf_multiplier: default_videotoolbox_crf_multiplier(),
+        nvenc_preset: default_nvenc_preset(),
+        nvenc_rc: default_nvenc_rc(),
+        qsv_preset: default_qsv_preset(),
+        amf_quality: default_amf_quality(),
+        amf_rc: default_amf_rc(),
+        threads_when_1_2_clips_min: default_threads_1_2_min(),
+        threads_when_1_2_clips_max: default_threads_1_2_max(),
+        threads_when_3_4_clips_min: default_threads_3_4_min(),
+        threads_when_3_4_clips_max: default_threads_3_4_max(),
+        threads_when_many_clips_min: default_threads_many_min(),
+        threads_when_many_clips_max: default_threads_many_max(),
+        av1_preset: default_av1_preset(),
+        av1_speed: default_av1_speed(),
+    }
+}
 fn default_thread_queue_size() -> usize { 512 }
 fn default_bufsize() -> String { "2M".to_string() }
 fn default_maxrate() -> String { "8M".to_string() }
@@ -139,17 +528,71 @@ fn default_threads_3_4_min() -> usize { 1 }
 fn default_threads_3_4_max() -> usize { 4 }
 fn default_threads_many_min() -> usize { 1 }
 fn default_threads_many_max() -> usize { 2 }
+fn default_av1_preset() -> u8 { 8 }
+fn default_av1_speed() -> u8 { 6 }
+fn default_audio_bitrate() -> String { "128k".to_string() }
+fn default_target_lufs() -> f64 { -16.0 }
+fn default_container() -> String { "mp4".to_string() }
+fn default_segment_duration() -> f64 { 4.0 }
+fn default_playlist_name() -> String { "playlist.m3u8".to_string() }
 fn default_filename() -> String { "video.mp4".to_string() }
 fn default_field_name() -> String { "".to_string() }
 fn default_max_clip_length() -> f64 { 15.0 }
 fn default_cleanup_interval() -> u64 { 300 }
 fn default_cleanup_max_age() -> u64 { 1800 }
+fn default_max_total_bytes() -> u64 { 10 * 1024 * 1024 * 1024 } // 10GB disk budget
+fn default_dedup_enabled() -> bool { true }
+fn default_dedup_tolerance_bits_per_frame() -> u32 { 10 }
+fn default_clip_dedup_tolerance_bits() -> u32 { 10 }
+fn default_strip_metadata() -> bool { true }
+fn default_min_free_memory_gb() -> f64 { 0.5 }
+fn default_max_process_rss_mb() -> f64 { 4096.0 }
+fn default_target_vmaf() -> f64 { 93.0 }
+fn default_min_crf() -> u8 { 18 }
+fn default_max_crf() -> u8 { 32 }
+fn default_probe_duration() -> f64 { 3.0 }
+fn default_probes() -> usize { 4 }
+fn default_adaptive_vmaf() -> f64 { 93.0 }
+fn default_adaptive_crf_min() -> u8 { 18 }
+fn default_adaptive_crf_max() -> u8 { 32 }
+fn default_adaptive_tolerance() -> f64 { 1.0 }
+fn default_adaptive_probe_frames() -> usize { 60 }
+fn default_retry_enabled() -> bool { true }
+fn default_retry_max_tries() -> u32 { 3 }
+fn default_retry_initial_backoff_ms() -> u64 { 500 }
+
+/// Shared by both `Config::load`'s fallback path and `impl Default for Config`,
+/// so `[ffmpeg.retry]` defaults to a real, enabled `EncodeRetryConfig` instead
+/// of `None` (which would leave `generate_clip_with_retry`'s hardware-fallback
+/// handling dead until an operator hand-writes the TOML section).
+fn default_retry_config() -> EncodeRetryConfig {
+    EncodeRetryConfig {
+        enabled: default_retry_enabled(),
+        max_tries: default_retry_max_tries(),
+        initial_backoff_ms: default_retry_initial_backoff_ms(),
+    }
+}
+fn default_scene_method() -> String { "standard".to_string() }
+fn default_min_scene_len() -> f64 { 1.0 }
+fn default_scene_threshold() -> f64 { 0.3 }
+fn default_scene_sensitivity() -> f64 { 2.5 }
+fn default_telegram_caption_template() -> String { "{video_id} / {clip_id}".to_string() }
+fn default_publish_max_concurrent_uploads() -> usize { 2 }
+fn default_publish_min_interval_ms() -> u64 { 1000 }
+fn default_eviction_policy() -> String { "oldest_first".to_string() }
+fn default_scene_snap_threshold() -> f64 { 0.4 }
+fn default_scene_snap_min_segment_len() -> f64 { 2.0 }
+fn default_scene_snap_max_segment_len() -> f64 { 30.0 }
+fn default_streaming_ingest_preview_length() -> f64 { 15.0 }
+fn default_streaming_ingest_max_concurrent() -> usize { 2 }
+fn default_hdr_mode() -> String { "tonemap_sdr".to_string() }
 
 #[derive(Clone, Debug)]
 pub struct Config {
     pub port: u16,
     pub upload_dir: PathBuf,
     pub output_dir: PathBuf,
+    pub db_path: PathBuf,
     pub max_file_size: u64,
     pub max_concurrent_clips: usize,
     pub upload_buffer_size: usize,
@@ -158,6 +601,19 @@ pub struct Config {
     pub optimization: OptimizationConfig,
     pub limits: LimitsConfig,
     pub server_defaults: ServerDefaultsConfig,
+    pub dedup: DedupConfig,
+    pub privacy: PrivacyConfig,
+    pub diagnostics: DiagnosticsConfig,
+    pub admission: AdmissionConfig,
+    pub scene_detection: SceneDetectionConfig,
+    pub output_format: OutputFormatConfig,
+    pub thread_policy: String,
+    pub validation: ValidationConfig,
+    pub output_profiles: Vec<OutputProfile>,
+    pub publish: PublishConfig,
+    pub retention: RetentionConfig,
+    pub scene_snap: SceneSnapConfig,
+    pub streaming_ingest: StreamingIngestConfig,
 }
 
 impl Config {
@@ -207,10 +663,13 @@ impl Config {
             .or_else(|| config_file.as_ref().map(|c| c.server.max_file_size))
             .unwrap_or(500 * 1024 * 1024);
 
+        let db_path = std::env::var("DB_PATH")
+            .map(PathBuf::from)
+            .unwrap_or_else(|_| base_dir.join("clipper.db"));
+
         // Performance config
-        let cpu_count = num_cpus::get();
-        let default_concurrent = (cpu_count.saturating_sub(1)).max(2).min(8);
-        
+        let default_concurrent = crate::system_info::recommended_concurrency();
+
         let max_concurrent_clips = std::env::var("MAX_CONCURRENT_CLIPS")
             .ok()
             .and_then(|v| v.parse().ok())
@@ -230,6 +689,10 @@ impl Config {
             .map(|c| c.performance.upload_log_interval)
             .unwrap_or(100);
 
+        let thread_policy = config_file.as_ref()
+            .map(|c| c.performance.thread_policy.clone())
+            .unwrap_or_else(default_thread_policy);
+
         // FFmpeg config
         let ffmpeg = config_file.as_ref()
             .map(|c| c.ffmpeg.clone())
@@ -243,8 +706,20 @@ impl Config {
                 tune: vec![], // No tune for better quality (removed fastdecode/zerolatency)
                 audio_codec: "copy".to_string(),
                 use_input_seeking: true,
-                additional_flags: vec!["+faststart".to_string(), "fflags=+genpts".to_string(), "avoid_negative_ts=make_zero".to_string()],
+                additional_flags: vec!["fflags=+genpts".to_string(), "avoid_negative_ts=make_zero".to_string()],
+                faststart: true,
                 advanced: None, // Will be populated below
+                target_quality: None,
+                adaptive_quality: None,
+                audio: None,
+                // Eagerly constructed (unlike target_quality/adaptive_quality/audio,
+                // which are genuinely opt-in) so its own field defaults take
+                // effect out of the box - otherwise `generate_clip_with_retry`'s
+                // hardware-fallback/stderr-classification handling would be
+                // dead code unless an operator hand-writes a `[ffmpeg.retry]`
+                // TOML section.
+                retry: Some(default_retry_config()),
+                hdr: None,
             });
 
         // Optimization config
@@ -267,6 +742,7 @@ impl Config {
                 default_max_clip_length: default_max_clip_length(),
                 cleanup_interval_seconds: default_cleanup_interval(),
                 cleanup_max_age_seconds: default_cleanup_max_age(),
+                max_total_bytes: default_max_total_bytes(),
             });
 
         // Server defaults config
@@ -277,31 +753,130 @@ impl Config {
                 default_field_name: default_field_name(),
             });
 
+        // Dedup config
+        let dedup = config_file
+            .as_ref()
+            .and_then(|c| c.dedup.clone())
+            .unwrap_or_else(|| DedupConfig {
+                enabled: default_dedup_enabled(),
+                tolerance_bits_per_frame: default_dedup_tolerance_bits_per_frame(),
+                clip_dedup_tolerance_bits: default_clip_dedup_tolerance_bits(),
+            });
+
+        // Privacy config
+        let privacy = config_file
+            .as_ref()
+            .and_then(|c| c.privacy.clone())
+            .unwrap_or_else(|| PrivacyConfig {
+                strip_metadata: default_strip_metadata(),
+            });
+
+        // Diagnostics config
+        let diagnostics = config_file
+            .as_ref()
+            .and_then(|c| c.diagnostics.clone())
+            .unwrap_or(DiagnosticsConfig { enable_state_dump: false });
+
+        // Admission control config - back-pressure thresholds for the busy gate
+        let admission = config_file
+            .as_ref()
+            .and_then(|c| c.admission.clone())
+            .unwrap_or_else(|| AdmissionConfig {
+                min_free_memory_gb: default_min_free_memory_gb(),
+                max_process_rss_mb: default_max_process_rss_mb(),
+            });
+
+        // Scene detection config
+        let scene_detection = config_file
+            .as_ref()
+            .and_then(|c| c.scene_detection.clone())
+            .unwrap_or_else(|| SceneDetectionConfig {
+                enabled: false,
+                method: default_scene_method(),
+                min_scene_len: default_min_scene_len(),
+                downscale_height: None,
+                threshold: default_scene_threshold(),
+                sensitivity: default_scene_sensitivity(),
+            });
+
+        // Output format config
+        let output_format = config_file
+            .as_ref()
+            .and_then(|c| c.output_format.clone())
+            .unwrap_or_else(|| OutputFormatConfig {
+                container: default_container(),
+                segment_duration: default_segment_duration(),
+                playlist_name: default_playlist_name(),
+            });
+
+        let validation = config_file
+            .as_ref()
+            .and_then(|c| c.validation.clone())
+            .unwrap_or_else(|| ValidationConfig {
+                allowed_video_codecs: default_allowed_video_codecs(),
+                allowed_containers: default_allowed_containers(),
+            });
+
+        let output_profiles = config_file
+            .as_ref()
+            .map(|c| c.output_profiles.clone())
+            .filter(|profiles| !profiles.is_empty())
+            .unwrap_or_else(default_output_profiles);
+
+        let publish = config_file
+            .as_ref()
+            .and_then(|c| c.publish.clone())
+            .unwrap_or_else(|| PublishConfig {
+                enabled: false,
+                telegram: None,
+                max_concurrent_uploads: default_publish_max_concurrent_uploads(),
+                min_interval_ms: default_publish_min_interval_ms(),
+            });
+
+        let retention = config_file
+            .as_ref()
+            .and_then(|c| c.retention.clone())
+            .unwrap_or_else(|| RetentionConfig {
+                eviction_policy: default_eviction_policy(),
+            });
+
+        let scene_snap = config_file
+            .as_ref()
+            .and_then(|c| c.scene_snap.clone())
+            .unwrap_or_else(|| SceneSnapConfig {
+                enabled: false,
+                threshold: default_scene_snap_threshold(),
+                min_segment_len: default_scene_snap_min_segment_len(),
+                max_segment_len: default_scene_snap_max_segment_len(),
+            });
+
+        let streaming_ingest = config_file
+            .as_ref()
+            .and_then(|c| c.streaming_ingest.clone())
+            .unwrap_or_else(|| StreamingIngestConfig {
+                enabled: false,
+                preview_clip_length: default_streaming_ingest_preview_length(),
+                max_concurrent: default_streaming_ingest_max_concurrent(),
+            });
+
         // Ensure FFmpeg advanced config is populated
         let ffmpeg = if ffmpeg.advanced.is_none() {
             FfmpegConfig {
-                advanced: Some(FfmpegAdvancedConfig {
-                    thread_queue_size: default_thread_queue_size(),
-                    bufsize: default_bufsize(),
-                    maxrate: default_maxrate(),
-                    gop_size: default_gop_size(),
-                    keyint_min: default_keyint_min(),
-                    default_video_codec: default_video_codec(),
-                    videotoolbox_quality_min: default_videotoolbox_quality_min(),
-                    videotoolbox_quality_max: default_videotoolbox_quality_max(),
-                    videotoolbox_crf_multiplier: default_videotoolbox_crf_multiplier(),
-                    nvenc_preset: default_nvenc_preset(),
-                    nvenc_rc: default_nvenc_rc(),
-                    qsv_preset: default_qsv_preset(),
-                    amf_quality: default_amf_quality(),
-                    amf_rc: default_amf_rc(),
-                    threads_when_1_2_clips_min: default_threads_1_2_min(),
-                    threads_when_1_2_clips_max: default_threads_1_2_max(),
-                    threads_when_3_4_clips_min: default_threads_3_4_min(),
-                    threads_when_3_4_clips_max: default_threads_3_4_max(),
-                    threads_when_many_clips_min: default_threads_many_min(),
-                    threads_when_many_clips_max: default_threads_many_max(),
-                }),
+                advanced: Some(default_ffmpeg_advanced_config()),
+                ..ffmpeg
+            }
+        } else {
+            ffmpeg
+        };
+
+        // Ensure retry has real defaults even when config.toml exists but its
+        // [ffmpeg] table omits [ffmpeg.retry] entirely - `#[serde(default)]`
+        // leaves the field None in that case just as it would if config.toml
+        // didn't exist at all, so the fallback-constructor default above
+        // isn't enough on its own to keep `generate_clip_with_retry` live.
+        let ffmpeg = if ffmpeg.retry.is_none() {
+            FfmpegConfig {
+                retry: Some(default_retry_config()),
                 ..ffmpeg
             }
         } else {
@@ -312,6 +887,7 @@ impl Config {
             port,
             upload_dir,
             output_dir,
+            db_path,
             max_file_size,
             max_concurrent_clips,
             upload_buffer_size,
@@ -320,9 +896,101 @@ impl Config {
             optimization,
             limits,
             server_defaults,
+            dedup,
+            privacy,
+            diagnostics,
+            admission,
+            scene_detection,
+            output_format,
+            thread_policy,
+            validation,
+            output_profiles,
+            publish,
+            retention,
+            scene_snap,
+            streaming_ingest,
         })
     }
 
+    /// Apply CLI flags on top of an already env/file-resolved Config, so the
+    /// effective precedence is CLI > env > config.toml > defaults.
+    pub fn apply_cli_overrides(mut self, cli: &crate::cli::CliArgs) -> Self {
+        if let Some(port) = cli.port {
+            self.port = port;
+        }
+        if let Some(ref upload_dir) = cli.upload_dir {
+            self.upload_dir = PathBuf::from(upload_dir);
+        }
+        if let Some(ref output_dir) = cli.output_dir {
+            self.output_dir = PathBuf::from(output_dir);
+        }
+        if let Some(max_file_size) = cli.max_file_size {
+            self.max_file_size = max_file_size;
+        }
+        if let Some(max_concurrent_clips) = cli.max_concurrent_clips {
+            self.max_concurrent_clips = max_concurrent_clips;
+        }
+        if let Some(crf) = cli.crf {
+            self.ffmpeg.crf = crf;
+        }
+        if let Some(ref preset) = cli.preset {
+            self.ffmpeg.preset = preset.clone();
+        }
+        if let Some(ref codec) = cli.codec {
+            let advanced = self.ffmpeg.advanced.get_or_insert_with(default_ffmpeg_advanced_config);
+            advanced.default_video_codec = codec.clone();
+        }
+        if let Some(target_vmaf) = cli.target_vmaf {
+            let target = self.ffmpeg.target_quality.get_or_insert_with(|| TargetQualityConfig {
+                enabled: true,
+                target_vmaf: default_target_vmaf(),
+                min_crf: default_min_crf(),
+                max_crf: default_max_crf(),
+                probe_duration: default_probe_duration(),
+                probes: default_probes(),
+            });
+            target.enabled = true;
+            target.target_vmaf = target_vmaf;
+        }
+        self
+    }
+
+    /// Serialize back to the `ConfigFile` shape so the fully-resolved config
+    /// (including auto-detected values like `max_concurrent_clips`) can be
+    /// round-tripped out as TOML via `--print-config`.
+    pub fn to_config_file(&self) -> ConfigFile {
+        ConfigFile {
+            server: ServerConfig {
+                port: self.port,
+                upload_dir: self.upload_dir.display().to_string(),
+                output_dir: self.output_dir.display().to_string(),
+                max_file_size: self.max_file_size,
+                defaults: Some(self.server_defaults.clone()),
+            },
+            performance: PerformanceConfig {
+                max_concurrent_clips: Some(self.max_concurrent_clips),
+                upload_buffer_size: self.upload_buffer_size,
+                upload_log_interval: self.upload_log_interval,
+                thread_policy: self.thread_policy.clone(),
+            },
+            ffmpeg: self.ffmpeg.clone(),
+            optimization: self.optimization.clone(),
+            limits: self.limits.clone(),
+            dedup: Some(self.dedup.clone()),
+            privacy: Some(self.privacy.clone()),
+            diagnostics: Some(self.diagnostics.clone()),
+            admission: Some(self.admission.clone()),
+            scene_detection: Some(self.scene_detection.clone()),
+            output_format: Some(self.output_format.clone()),
+            validation: Some(self.validation.clone()),
+            output_profiles: self.output_profiles.clone(),
+            publish: Some(self.publish.clone()),
+            retention: Some(self.retention.clone()),
+            scene_snap: Some(self.scene_snap.clone()),
+            streaming_ingest: Some(self.streaming_ingest.clone()),
+        }
+    }
+
     // Backward compatibility - deprecated, use load() instead
     #[deprecated(note = "Use Config::load() instead")]
     #[allow(dead_code)]
@@ -338,17 +1006,18 @@ impl Default for Config {
     fn default() -> Self {
         let base_dir = std::env::current_dir()
             .unwrap_or_else(|_| PathBuf::from("."));
-        let cpu_count = num_cpus::get();
-        let default_concurrent = (cpu_count.saturating_sub(1)).max(2).min(8);
+        let default_concurrent = crate::system_info::recommended_concurrency();
 
         Self {
             port: 3000,
             upload_dir: base_dir.join("uploads"),
             output_dir: base_dir.join("clips"),
+            db_path: base_dir.join("clipper.db"),
             max_file_size: 500 * 1024 * 1024,
             max_concurrent_clips: default_concurrent,
             upload_buffer_size: 524288, // 512KB for better I/O performance with large files
             upload_log_interval: 100,
+            thread_policy: default_thread_policy(),
             ffmpeg: FfmpegConfig {
                 preset: "medium".to_string(), // Good balance of quality and speed
                 crf: 20, // High quality (lower is better, 18-23 range recommended)
@@ -359,29 +1028,14 @@ impl Default for Config {
                 tune: vec![], // No tune for better quality (removed fastdecode/zerolatency)
                 audio_codec: "copy".to_string(),
                 use_input_seeking: true,
-                additional_flags: vec!["+faststart".to_string(), "fflags=+genpts".to_string(), "avoid_negative_ts=make_zero".to_string()],
-                advanced: Some(FfmpegAdvancedConfig {
-                    thread_queue_size: default_thread_queue_size(),
-                    bufsize: default_bufsize(),
-                    maxrate: default_maxrate(),
-                    gop_size: default_gop_size(),
-                    keyint_min: default_keyint_min(),
-                    default_video_codec: default_video_codec(),
-                    videotoolbox_quality_min: default_videotoolbox_quality_min(),
-                    videotoolbox_quality_max: default_videotoolbox_quality_max(),
-                    videotoolbox_crf_multiplier: default_videotoolbox_crf_multiplier(),
-                    nvenc_preset: default_nvenc_preset(),
-                    nvenc_rc: default_nvenc_rc(),
-                    qsv_preset: default_qsv_preset(),
-                    amf_quality: default_amf_quality(),
-                    amf_rc: default_amf_rc(),
-                    threads_when_1_2_clips_min: default_threads_1_2_min(),
-                    threads_when_1_2_clips_max: default_threads_1_2_max(),
-                    threads_when_3_4_clips_min: default_threads_3_4_min(),
-                    threads_when_3_4_clips_max: default_threads_3_4_max(),
-                    threads_when_many_clips_min: default_threads_many_min(),
-                    threads_when_many_clips_max: default_threads_many_max(),
-                }),
+                additional_flags: vec!["fflags=+genpts".to_string(), "avoid_negative_ts=make_zero".to_string()],
+                faststart: true,
+                advanced: Some(default_ffmpeg_advanced_config()),
+                target_quality: None,
+                adaptive_quality: None,
+                audio: None,
+                retry: Some(default_retry_config()),
+                hdr: None,
             },
             optimization: OptimizationConfig {
                 enable_buffered_uploads: true,
@@ -396,11 +1050,63 @@ impl Default for Config {
                 default_max_clip_length: default_max_clip_length(),
                 cleanup_interval_seconds: default_cleanup_interval(),
                 cleanup_max_age_seconds: default_cleanup_max_age(),
+                max_total_bytes: default_max_total_bytes(),
             },
             server_defaults: ServerDefaultsConfig {
                 default_filename: default_filename(),
                 default_field_name: default_field_name(),
             },
+            dedup: DedupConfig {
+                enabled: default_dedup_enabled(),
+                tolerance_bits_per_frame: default_dedup_tolerance_bits_per_frame(),
+                clip_dedup_tolerance_bits: default_clip_dedup_tolerance_bits(),
+            },
+            privacy: PrivacyConfig {
+                strip_metadata: default_strip_metadata(),
+            },
+            diagnostics: DiagnosticsConfig { enable_state_dump: false },
+            admission: AdmissionConfig {
+                min_free_memory_gb: default_min_free_memory_gb(),
+                max_process_rss_mb: default_max_process_rss_mb(),
+            },
+            scene_detection: SceneDetectionConfig {
+                enabled: false,
+                method: default_scene_method(),
+                min_scene_len: default_min_scene_len(),
+                downscale_height: None,
+                threshold: default_scene_threshold(),
+                sensitivity: default_scene_sensitivity(),
+            },
+            output_format: OutputFormatConfig {
+                container: default_container(),
+                segment_duration: default_segment_duration(),
+                playlist_name: default_playlist_name(),
+            },
+            validation: ValidationConfig {
+                allowed_video_codecs: default_allowed_video_codecs(),
+                allowed_containers: default_allowed_containers(),
+            },
+            output_profiles: default_output_profiles(),
+            publish: PublishConfig {
+                enabled: false,
+                telegram: None,
+                max_concurrent_uploads: default_publish_max_concurrent_uploads(),
+                min_interval_ms: default_publish_min_interval_ms(),
+            },
+            retention: RetentionConfig {
+                eviction_policy: default_eviction_policy(),
+            },
+            scene_snap: SceneSnapConfig {
+                enabled: false,
+                threshold: default_scene_snap_threshold(),
+                min_segment_len: default_scene_snap_min_segment_len(),
+                max_segment_len: default_scene_snap_max_segment_len(),
+            },
+            streaming_ingest: StreamingIngestConfig {
+                enabled: false,
+                preview_clip_length: default_streaming_ingest_preview_length(),
+                max_concurrent: default_streaming_ingest_max_concurrent(),
+            },
         }
     }
 }
@@ -0,0 +1,345 @@
+use crate::models::{Clip, VideoMetadata};
+use anyhow::{Context, Result};
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePoolOptions};
+use sqlx::{Row, SqlitePool};
+use std::path::Path;
+use std::str::FromStr;
+use std::time::UNIX_EPOCH;
+use tracing::info;
+
+/// Current schema version, tracked via SQLite's `PRAGMA user_version` rather
+/// than a migrations table - there's only ever one writer process and a
+/// handful of forward-only steps, so this keeps things simple.
+const SCHEMA_VERSION: i64 = 3;
+
+/// Persistent metadata store backed by SQLite. `AppState.videos`/`clip_cache`
+/// remain the hot-path in-memory cache; this is the write-through layer that
+/// survives restarts and lets multiple processes share upload/clip state.
+#[derive(Clone)]
+pub struct Db {
+    pool: SqlitePool,
+}
+
+impl Db {
+    pub async fn connect(path: &Path) -> Result<Self> {
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await.ok();
+        }
+
+        let options = SqliteConnectOptions::from_str(&format!("sqlite://{}", path.display()))
+            .context("invalid sqlite path")?
+            .create_if_missing(true);
+
+        let pool = SqlitePoolOptions::new()
+            .max_connections(5)
+            .connect_with(options)
+            .await
+            .context("failed to open sqlite database")?;
+
+        let db = Self { pool };
+        db.migrate().await?;
+        Ok(db)
+    }
+
+    async fn migrate(&self) -> Result<()> {
+        let current_version: i64 = sqlx::query("PRAGMA user_version")
+            .fetch_one(&self.pool)
+            .await?
+            .get(0);
+
+        if current_version < 1 {
+            sqlx::query(
+                "CREATE TABLE IF NOT EXISTS videos (
+                    id TEXT PRIMARY KEY,
+                    file_path TEXT NOT NULL,
+                    duration REAL NOT NULL,
+                    original_name TEXT NOT NULL,
+                    file_size INTEGER NOT NULL,
+                    uploaded_at INTEGER NOT NULL,
+                    width INTEGER,
+                    height INTEGER,
+                    codec TEXT,
+                    pix_fmt TEXT,
+                    container TEXT,
+                    frame_count INTEGER,
+                    audio_codec TEXT,
+                    frame_rate REAL,
+                    bit_rate INTEGER,
+                    fingerprint BLOB
+                )",
+            )
+            .execute(&self.pool)
+            .await?;
+
+            sqlx::query(
+                "CREATE TABLE IF NOT EXISTS clips (
+                    id TEXT NOT NULL,
+                    video_id TEXT NOT NULL REFERENCES videos(id),
+                    url TEXT NOT NULL,
+                    thumbnail_url TEXT NOT NULL,
+                    duration REAL NOT NULL,
+                    width INTEGER,
+                    height INTEGER,
+                    output_path TEXT NOT NULL,
+                    PRIMARY KEY (video_id, id)
+                )",
+            )
+            .execute(&self.pool)
+            .await?;
+
+            sqlx::query("CREATE INDEX IF NOT EXISTS idx_clips_video_id ON clips(video_id)")
+                .execute(&self.pool)
+                .await?;
+        }
+
+        if current_version < 2 {
+            // Multi-codec/multi-resolution output_profiles: which preset (if any)
+            // produced this rendition, and what codec it was encoded with.
+            sqlx::query("ALTER TABLE clips ADD COLUMN profile TEXT")
+                .execute(&self.pool)
+                .await?;
+            sqlx::query("ALTER TABLE clips ADD COLUMN codec TEXT")
+                .execute(&self.pool)
+                .await?;
+        }
+
+        if current_version < 3 {
+            // Scope cached clips by request shape (see `ClipRequest::request_shape_hash`),
+            // not just video_id, so a repeat /clip call for the same video with
+            // different max_length/mode/output_profiles/output_container gets
+            // its own row instead of colliding on (video_id, id) with an
+            // earlier call's clips. SQLite can't ALTER a PRIMARY KEY in place,
+            // so rebuild the table; existing rows backfill request_key = ''
+            // (the "no distinguishing parameters" key), which is harmless -
+            // they just won't be matched by a differently-keyed request.
+            sqlx::query("ALTER TABLE clips RENAME TO clips_old")
+                .execute(&self.pool)
+                .await?;
+            sqlx::query(
+                "CREATE TABLE clips (
+                    id TEXT NOT NULL,
+                    video_id TEXT NOT NULL REFERENCES videos(id),
+                    request_key TEXT NOT NULL DEFAULT '',
+                    url TEXT NOT NULL,
+                    thumbnail_url TEXT NOT NULL,
+                    duration REAL NOT NULL,
+                    width INTEGER,
+                    height INTEGER,
+                    output_path TEXT NOT NULL,
+                    profile TEXT,
+                    codec TEXT,
+                    PRIMARY KEY (video_id, request_key, id)
+                )",
+            )
+            .execute(&self.pool)
+            .await?;
+            sqlx::query(
+                "INSERT INTO clips (id, video_id, request_key, url, thumbnail_url, duration, width, height, output_path, profile, codec)
+                 SELECT id, video_id, '', url, thumbnail_url, duration, width, height, output_path, profile, codec FROM clips_old",
+            )
+            .execute(&self.pool)
+            .await?;
+            sqlx::query("DROP TABLE clips_old")
+                .execute(&self.pool)
+                .await?;
+            sqlx::query("CREATE INDEX IF NOT EXISTS idx_clips_video_request ON clips(video_id, request_key)")
+                .execute(&self.pool)
+                .await?;
+        }
+
+        // Future schema changes append an `if current_version < N` block here
+        // and bump SCHEMA_VERSION, same as the block above.
+
+        if current_version < SCHEMA_VERSION {
+            sqlx::query(&format!("PRAGMA user_version = {}", SCHEMA_VERSION))
+                .execute(&self.pool)
+                .await?;
+            info!(
+                "[db] ‚úÖ Migrated schema from version {} to {}",
+                current_version, SCHEMA_VERSION
+            );
+        }
+
+        Ok(())
+    }
+
+    pub async fn upsert_video(&self, video: &VideoMetadata) -> Result<()> {
+        let uploaded_at = video
+            .uploaded_at
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs() as i64;
+
+        sqlx::query(
+            "INSERT INTO videos (
+                id, file_path, duration, original_name, file_size, uploaded_at,
+                width, height, codec, pix_fmt, container, frame_count,
+                audio_codec, frame_rate, bit_rate, fingerprint
+            ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            ON CONFLICT(id) DO UPDATE SET
+                file_path = excluded.file_path,
+                duration = excluded.duration,
+                original_name = excluded.original_name,
+                file_size = excluded.file_size,
+                uploaded_at = excluded.uploaded_at,
+                width = excluded.width,
+                height = excluded.height,
+                codec = excluded.codec,
+                pix_fmt = excluded.pix_fmt,
+                container = excluded.container,
+                frame_count = excluded.frame_count,
+                audio_codec = excluded.audio_codec,
+                frame_rate = excluded.frame_rate,
+                bit_rate = excluded.bit_rate,
+                fingerprint = excluded.fingerprint",
+        )
+        .bind(&video.id)
+        .bind(video.file_path.display().to_string())
+        .bind(video.duration)
+        .bind(&video.original_name)
+        .bind(video.file_size as i64)
+        .bind(uploaded_at)
+        .bind(video.width.map(|v| v as i64))
+        .bind(video.height.map(|v| v as i64))
+        .bind(&video.codec)
+        .bind(&video.pix_fmt)
+        .bind(&video.container)
+        .bind(video.frame_count.map(|v| v as i64))
+        .bind(&video.audio_codec)
+        .bind(video.frame_rate)
+        .bind(video.bit_rate.map(|v| v as i64))
+        .bind(video.fingerprint.as_deref())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn get_video(&self, video_id: &str) -> Result<Option<VideoMetadata>> {
+        let row = sqlx::query(
+            "SELECT id, file_path, duration, original_name, file_size, uploaded_at,
+                    width, height, codec, pix_fmt, container, frame_count,
+                    audio_codec, frame_rate, bit_rate, fingerprint
+             FROM videos WHERE id = ?",
+        )
+        .bind(video_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(row.map(video_from_row))
+    }
+
+    /// All persisted videos, used to rehydrate `AppState.videos` and
+    /// `dedup_tree` from the metadata store at startup so a restart doesn't
+    /// silently forget every video uploaded before it.
+    pub async fn get_all_videos(&self) -> Result<Vec<VideoMetadata>> {
+        let rows = sqlx::query(
+            "SELECT id, file_path, duration, original_name, file_size, uploaded_at,
+                    width, height, codec, pix_fmt, container, frame_count,
+                    audio_codec, frame_rate, bit_rate, fingerprint
+             FROM videos",
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.into_iter().map(video_from_row).collect())
+    }
+
+    pub async fn delete_video(&self, video_id: &str) -> Result<()> {
+        sqlx::query("DELETE FROM clips WHERE video_id = ?")
+            .bind(video_id)
+            .execute(&self.pool)
+            .await?;
+        sqlx::query("DELETE FROM videos WHERE id = ?")
+            .bind(video_id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    /// Record generated clips for `video_id` under `request_key` (see
+    /// `ClipRequest::request_shape_hash`). `output_path` is stored as the served
+    /// `/clips/...` URL, which already uniquely locates the file under
+    /// `config.output_dir` without duplicating the absolute disk path.
+    pub async fn insert_clips(&self, video_id: &str, request_key: &str, clips: &[Clip]) -> Result<()> {
+        for clip in clips {
+            sqlx::query(
+                "INSERT INTO clips (id, video_id, request_key, url, thumbnail_url, duration, width, height, output_path, profile, codec)
+                 VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+                 ON CONFLICT(video_id, request_key, id) DO UPDATE SET
+                    url = excluded.url,
+                    thumbnail_url = excluded.thumbnail_url,
+                    duration = excluded.duration,
+                    width = excluded.width,
+                    height = excluded.height,
+                    output_path = excluded.output_path,
+                    profile = excluded.profile,
+                    codec = excluded.codec",
+            )
+            .bind(&clip.id)
+            .bind(video_id)
+            .bind(request_key)
+            .bind(&clip.url)
+            .bind(&clip.thumbnail_url)
+            .bind(clip.duration)
+            .bind(clip.width.map(|v| v as i64))
+            .bind(clip.height.map(|v| v as i64))
+            .bind(&clip.url)
+            .bind(&clip.profile)
+            .bind(&clip.codec)
+            .execute(&self.pool)
+            .await?;
+        }
+        Ok(())
+    }
+
+    pub async fn get_clips(&self, video_id: &str, request_key: &str) -> Result<Vec<Clip>> {
+        let rows = sqlx::query(
+            "SELECT id, url, thumbnail_url, duration, width, height, profile, codec FROM clips WHERE video_id = ? AND request_key = ?",
+        )
+        .bind(video_id)
+        .bind(request_key)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| Clip {
+                id: row.get("id"),
+                url: row.get("url"),
+                thumbnail_url: row.get("thumbnail_url"),
+                duration: row.get("duration"),
+                width: row.get::<Option<i64>, _>("width").map(|v| v as u32),
+                profile: row.get("profile"),
+                codec: row.get("codec"),
+                height: row.get::<Option<i64>, _>("height").map(|v| v as u32),
+            })
+            .collect())
+    }
+}
+
+/// Shared row->struct mapping for `get_video`/`get_all_videos`, both of which
+/// select the same `videos` columns.
+fn video_from_row(row: sqlx::sqlite::SqliteRow) -> VideoMetadata {
+    let uploaded_at_secs: i64 = row.get("uploaded_at");
+    let file_path: String = row.get("file_path");
+
+    VideoMetadata {
+        id: row.get("id"),
+        file_path: file_path.into(),
+        duration: row.get("duration"),
+        original_name: row.get("original_name"),
+        file_size: row.get::<i64, _>("file_size") as u64,
+        uploaded_at: UNIX_EPOCH + std::time::Duration::from_secs(uploaded_at_secs.max(0) as u64),
+        width: row.get::<Option<i64>, _>("width").map(|v| v as u32),
+        height: row.get::<Option<i64>, _>("height").map(|v| v as u32),
+        codec: row.get("codec"),
+        pix_fmt: row.get("pix_fmt"),
+        container: row.get("container"),
+        frame_count: row.get::<Option<i64>, _>("frame_count").map(|v| v as u64),
+        audio_codec: row.get("audio_codec"),
+        frame_rate: row.get("frame_rate"),
+        bit_rate: row.get::<Option<i64>, _>("bit_rate").map(|v| v as u64),
+        fingerprint: row.get("fingerprint"),
+    }
+}
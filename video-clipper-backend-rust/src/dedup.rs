@@ -0,0 +1,271 @@
+use anyhow::{Context, Result};
+use std::path::Path;
+use std::process::Stdio;
+use tokio::process::Command;
+
+/// Side length of the grayscale thumbnail each sampled frame is downscaled to
+const HASH_GRID: u32 = 32;
+/// Number of evenly-spaced frames sampled across the video to build one fingerprint
+const FRAMES_PER_FINGERPRINT: usize = 10;
+
+/// Compute a perceptual fingerprint for a video: sample `FRAMES_PER_FINGERPRINT`
+/// evenly-spaced frames, downscale each to a small grayscale thumbnail, compute
+/// a difference-hash per frame (each pixel compared to its right neighbor), and
+/// concatenate all frames' bits into one fingerprint.
+pub async fn compute_fingerprint(path: &Path, duration: f64) -> Result<Vec<u8>> {
+    let mut bits = Vec::with_capacity(FRAMES_PER_FINGERPRINT * (HASH_GRID * (HASH_GRID - 1)) as usize);
+
+    for i in 0..FRAMES_PER_FINGERPRINT {
+        // Sample the middle of each of FRAMES_PER_FINGERPRINT equal slices, avoiding
+        // the very first/last frame where black fades are common
+        let t = duration * (i as f64 + 0.5) / FRAMES_PER_FINGERPRINT as f64;
+        bits.extend(dhash_frame(path, t).await?);
+    }
+
+    Ok(pack_bits(&bits))
+}
+
+/// Extract a single grayscale frame at `time` and compute its difference-hash bits
+async fn dhash_frame(path: &Path, time: f64) -> Result<Vec<bool>> {
+    let output = Command::new("ffmpeg")
+        .arg("-ss")
+        .arg(time.to_string())
+        .arg("-i")
+        .arg(path)
+        .arg("-vframes")
+        .arg("1")
+        .arg("-vf")
+        .arg(format!("scale={}:{}:flags=area,format=gray", HASH_GRID, HASH_GRID))
+        .arg("-f")
+        .arg("rawvideo")
+        .arg("-")
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .output()
+        .await
+        .context("Failed to execute ffmpeg for perceptual-hash frame extraction")?;
+
+    let pixel_count = (HASH_GRID * HASH_GRID) as usize;
+    if !output.status.success() || output.stdout.len() < pixel_count {
+        anyhow::bail!("ffmpeg did not produce a gray frame at {:.2}s for hashing", time);
+    }
+
+    let pixels = &output.stdout[..pixel_count];
+    let mut bits = Vec::with_capacity((HASH_GRID * (HASH_GRID - 1)) as usize);
+    for row in 0..HASH_GRID {
+        for col in 0..HASH_GRID - 1 {
+            let left = pixels[(row * HASH_GRID + col) as usize];
+            let right = pixels[(row * HASH_GRID + col + 1) as usize];
+            bits.push(left > right);
+        }
+    }
+    Ok(bits)
+}
+
+fn pack_bits(bits: &[bool]) -> Vec<u8> {
+    bits.chunks(8)
+        .map(|chunk| {
+            chunk
+                .iter()
+                .enumerate()
+                .fold(0u8, |acc, (i, &bit)| acc | ((bit as u8) << i))
+        })
+        .collect()
+}
+
+/// Frames sampled per clip for the compact per-clip hash below
+const CLIP_HASH_FRAMES: usize = 4;
+/// Grayscale downscale side length each sampled frame is reduced to
+const CLIP_HASH_GRID: u32 = 32;
+/// Side length, in pixels, of the square blocks each frame is pooled into -
+/// `(CLIP_HASH_GRID / CLIP_HASH_BLOCK_SIDE)^2` = 16 blocks per frame, one bit
+/// each, so `CLIP_HASH_FRAMES` frames pack into exactly 64 bits (8 bytes)
+const CLIP_HASH_BLOCK_SIDE: u32 = 8;
+
+/// Compute a compact 64-bit spatial-temporal hash for a single generated
+/// clip: sample `CLIP_HASH_FRAMES` evenly-spaced frames, downscale each to
+/// `CLIP_HASH_GRID`x`CLIP_HASH_GRID` grayscale, pool into a 4x4 grid of 8x8-pixel blocks,
+/// and set one bit per block for whether it's brighter than the frame's
+/// overall mean. Near-identical clips (e.g. a repeated intro or replay) land
+/// within a small Hamming distance of each other, far apart from everything
+/// else, same as `compute_fingerprint`'s whole-video hash but sized down to
+/// fit a 64-bit budget as requested for per-clip dedup.
+pub async fn compute_clip_hash(path: &Path, duration: f64) -> Result<Vec<u8>> {
+    let mut bits = Vec::with_capacity(CLIP_HASH_FRAMES * (CLIP_HASH_GRID / CLIP_HASH_BLOCK_SIDE).pow(2) as usize);
+
+    for i in 0..CLIP_HASH_FRAMES {
+        let t = duration * (i as f64 + 0.5) / CLIP_HASH_FRAMES as f64;
+        bits.extend(mean_threshold_frame(path, t).await?);
+    }
+
+    Ok(pack_bits(&bits))
+}
+
+/// Extract a single grayscale frame at `time` and return one bit per 8x8
+/// block: whether that block's mean is brighter than the whole frame's mean.
+async fn mean_threshold_frame(path: &Path, time: f64) -> Result<Vec<bool>> {
+    let output = Command::new("ffmpeg")
+        .arg("-ss")
+        .arg(time.to_string())
+        .arg("-i")
+        .arg(path)
+        .arg("-vframes")
+        .arg("1")
+        .arg("-vf")
+        .arg(format!(
+            "scale={}:{}:flags=area,format=gray",
+            CLIP_HASH_GRID, CLIP_HASH_GRID
+        ))
+        .arg("-f")
+        .arg("rawvideo")
+        .arg("-")
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .output()
+        .await
+        .context("Failed to execute ffmpeg for clip-hash frame extraction")?;
+
+    let pixel_count = (CLIP_HASH_GRID * CLIP_HASH_GRID) as usize;
+    if !output.status.success() || output.stdout.len() < pixel_count {
+        anyhow::bail!("ffmpeg did not produce a gray frame at {:.2}s for clip hashing", time);
+    }
+
+    let pixels = &output.stdout[..pixel_count];
+    let overall_mean =
+        pixels.iter().map(|&p| p as f64).sum::<f64>() / pixel_count as f64;
+
+    let blocks_per_side = CLIP_HASH_GRID / CLIP_HASH_BLOCK_SIDE;
+    let mut bits = Vec::with_capacity((blocks_per_side * blocks_per_side) as usize);
+    for block_row in 0..blocks_per_side {
+        for block_col in 0..blocks_per_side {
+            let mut sum = 0.0;
+            for y in 0..CLIP_HASH_BLOCK_SIDE {
+                for x in 0..CLIP_HASH_BLOCK_SIDE {
+                    let px = block_col * CLIP_HASH_BLOCK_SIDE + x;
+                    let py = block_row * CLIP_HASH_BLOCK_SIDE + y;
+                    sum += pixels[(py * CLIP_HASH_GRID + px) as usize] as f64;
+                }
+            }
+            let block_mean = sum / (CLIP_HASH_BLOCK_SIDE * CLIP_HASH_BLOCK_SIDE) as f64;
+            bits.push(block_mean > overall_mean);
+        }
+    }
+    Ok(bits)
+}
+
+/// Hamming distance (popcount of XOR) between two equal-length fingerprints
+pub fn hamming_distance(a: &[u8], b: &[u8]) -> u32 {
+    a.iter().zip(b.iter()).map(|(x, y)| (x ^ y).count_ones()).sum()
+}
+
+/// Total allowed distance for a fingerprint match, scaled by the per-frame tolerance
+pub fn total_tolerance(tolerance_bits_per_frame: u32) -> u32 {
+    tolerance_bits_per_frame * FRAMES_PER_FINGERPRINT as u32
+}
+
+struct BkNode {
+    video_id: String,
+    fingerprint: Vec<u8>,
+    // Children keyed by their Hamming distance from this node
+    children: Vec<(u32, Box<BkNode>)>,
+}
+
+/// A BK-tree indexing fingerprints by Hamming distance so near-duplicate lookups
+/// run in roughly O(log n) instead of scanning every stored video.
+#[derive(Default)]
+pub struct BkTree {
+    root: Option<Box<BkNode>>,
+}
+
+impl BkTree {
+    pub fn new() -> Self {
+        Self { root: None }
+    }
+
+    /// Insert a fingerprint, placing it as a child at the edge labeled by its
+    /// distance to the current node (recursing down on a distance collision)
+    pub fn insert(&mut self, video_id: String, fingerprint: Vec<u8>) {
+        let new_node = Box::new(BkNode {
+            video_id,
+            fingerprint,
+            children: Vec::new(),
+        });
+        match &mut self.root {
+            None => self.root = Some(new_node),
+            Some(root) => Self::insert_node(root, new_node),
+        }
+    }
+
+    fn insert_node(node: &mut BkNode, new_node: Box<BkNode>) {
+        let dist = hamming_distance(&node.fingerprint, &new_node.fingerprint);
+        for (edge_dist, child) in node.children.iter_mut() {
+            if *edge_dist == dist {
+                Self::insert_node(child, new_node);
+                return;
+            }
+        }
+        node.children.push((dist, new_node));
+    }
+
+    /// Find the closest existing fingerprint within `tolerance` bits, if any.
+    /// Only recurses into children whose edge distance `d` satisfies
+    /// `|d - dist(query, node)| <= tolerance`.
+    pub fn find_within(&self, fingerprint: &[u8], tolerance: u32) -> Option<String> {
+        let root = self.root.as_ref()?;
+        let mut best: Option<(u32, &str)> = None;
+        Self::query_node(root, fingerprint, tolerance, &mut best);
+        best.map(|(_, id)| id.to_string())
+    }
+
+    fn query_node<'a>(
+        node: &'a BkNode,
+        fingerprint: &[u8],
+        tolerance: u32,
+        best: &mut Option<(u32, &'a str)>,
+    ) {
+        let dist = hamming_distance(&node.fingerprint, fingerprint);
+        if dist <= tolerance && best.map_or(true, |(best_dist, _)| dist < best_dist) {
+            *best = Some((dist, &node.video_id));
+        }
+        for (edge_dist, child) in &node.children {
+            let lo = edge_dist.saturating_sub(tolerance);
+            let hi = edge_dist + tolerance;
+            if dist >= lo && dist <= hi {
+                Self::query_node(child, fingerprint, tolerance, best);
+            }
+        }
+    }
+
+    /// Find every existing fingerprint within `tolerance` bits, sorted nearest
+    /// match first. Used by `GET /duplicates/{video_id}`
+    /// to surface all near-matches rather than just the closest one.
+    pub fn find_all_within(&self, fingerprint: &[u8], tolerance: u32) -> Vec<(String, u32)> {
+        let mut matches = Vec::new();
+        if let Some(root) = &self.root {
+            Self::collect_within(root, fingerprint, tolerance, &mut matches);
+        }
+        matches.sort_by_key(|(_, dist)| *dist);
+        matches
+    }
+
+    fn collect_within(
+        node: &BkNode,
+        fingerprint: &[u8],
+        tolerance: u32,
+        matches: &mut Vec<(String, u32)>,
+    ) {
+        let dist = hamming_distance(&node.fingerprint, fingerprint);
+        if dist <= tolerance {
+            matches.push((node.video_id.clone(), dist));
+        }
+        for (edge_dist, child) in &node.children {
+            let lo = edge_dist.saturating_sub(tolerance);
+            let hi = edge_dist + tolerance;
+            if dist >= lo && dist <= hi {
+                Self::collect_within(child, fingerprint, tolerance, matches);
+            }
+        }
+    }
+}
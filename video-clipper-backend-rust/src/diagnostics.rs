@@ -0,0 +1,126 @@
+use crate::models::{AppState, VideoMetadata};
+use crate::system_info::{self, SystemInfo};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::path::PathBuf;
+use tokio::fs;
+use tracing::warn;
+
+#[derive(Serialize)]
+pub struct FileEntry {
+    pub path: PathBuf,
+    pub sha256: String,
+    pub size: u64,
+    pub modified_unix_secs: u64,
+}
+
+#[derive(Serialize)]
+pub struct StateDumpCounts {
+    pub video_count: usize,
+    pub total_bytes: u64,
+    // Files on disk that have no corresponding entry in `AppState.videos`
+    pub orphaned_files: usize,
+    // Entries in `AppState.videos` whose backing file no longer exists on disk
+    pub orphaned_metadata: usize,
+}
+
+#[derive(Serialize)]
+pub struct StateDump {
+    pub system_info: SystemInfo,
+    pub videos: Vec<VideoMetadata>,
+    pub files: Vec<FileEntry>,
+    pub counts: StateDumpCounts,
+}
+
+/// Walk a directory tree (reusing the same traversal shape as the cleanup
+/// subsystem) and checksum every file found
+async fn walk_and_checksum(dir: &PathBuf, out: &mut Vec<FileEntry>) {
+    let mut dirs_to_process = vec![dir.clone()];
+
+    while let Some(current_dir) = dirs_to_process.pop() {
+        let mut entries = match fs::read_dir(&current_dir).await {
+            Ok(entries) => entries,
+            Err(_) => continue,
+        };
+
+        while let Ok(Some(entry)) = entries.next_entry().await {
+            let path = entry.path();
+            let metadata = match fs::metadata(&path).await {
+                Ok(m) => m,
+                Err(e) => {
+                    warn!("[diagnostics] Failed to stat {:?}: {}", path, e);
+                    continue;
+                }
+            };
+
+            if metadata.is_dir() {
+                dirs_to_process.push(path);
+                continue;
+            }
+
+            let sha256 = match fs::read(&path).await {
+                Ok(bytes) => {
+                    let mut hasher = Sha256::new();
+                    hasher.update(&bytes);
+                    format!("{:x}", hasher.finalize())
+                }
+                Err(e) => {
+                    warn!("[diagnostics] Failed to read {:?} for checksum: {}", path, e);
+                    continue;
+                }
+            };
+
+            let modified_unix_secs = metadata
+                .modified()
+                .ok()
+                .and_then(|m| m.duration_since(std::time::UNIX_EPOCH).ok())
+                .map(|d| d.as_secs())
+                .unwrap_or(0);
+
+            out.push(FileEntry {
+                path,
+                sha256,
+                size: metadata.len(),
+                modified_unix_secs,
+            });
+        }
+    }
+}
+
+/// Snapshot of live server state: system info, in-memory video metadata, and
+/// a checksummed walk of every file currently on disk, for operators to
+/// diagnose leaks or verify cleanup correctness.
+pub async fn dump_state(state: &AppState) -> StateDump {
+    let videos: Vec<VideoMetadata> = state.videos.read().await.values().cloned().collect();
+
+    let mut files = Vec::new();
+    walk_and_checksum(&state.config.output_dir, &mut files).await;
+    walk_and_checksum(&state.config.upload_dir, &mut files).await;
+
+    let known_paths: HashSet<&PathBuf> = videos.iter().map(|v| &v.file_path).collect();
+    let file_paths: HashSet<&PathBuf> = files.iter().map(|f| &f.path).collect();
+
+    let orphaned_files = files
+        .iter()
+        .filter(|f| !known_paths.contains(&f.path))
+        .count();
+    let orphaned_metadata = videos
+        .iter()
+        .filter(|v| !file_paths.contains(&v.file_path))
+        .count();
+
+    let total_bytes = files.iter().map(|f| f.size).sum();
+
+    StateDump {
+        system_info: system_info::get_system_info(),
+        counts: StateDumpCounts {
+            video_count: videos.len(),
+            total_bytes,
+            orphaned_files,
+            orphaned_metadata,
+        },
+        videos,
+        files,
+    }
+}
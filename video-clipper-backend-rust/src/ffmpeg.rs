@@ -1,10 +1,293 @@
 use anyhow::{Context, Result};
+use serde::Deserialize;
 use std::path::Path;
 use std::process::Stdio;
 use std::sync::Mutex;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt};
 use tokio::process::Command;
 use tracing::{info, warn};
 
+/// Structured ffprobe/ffmpeg failure modes, distinct from the catch-all `anyhow::Error`
+/// used elsewhere in this module so callers can match on what actually went wrong.
+#[derive(Debug)]
+pub enum FfmpegError {
+    BinaryNotFound(String),
+    NonZeroExit { command: String, stderr: String },
+    BadJson(String),
+}
+
+impl std::fmt::Display for FfmpegError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FfmpegError::BinaryNotFound(bin) => write!(f, "{} not found on PATH", bin),
+            FfmpegError::NonZeroExit { command, stderr } => {
+                write!(f, "{} exited with an error: {}", command, stderr)
+            }
+            FfmpegError::BadJson(msg) => write!(f, "failed to parse ffprobe JSON: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for FfmpegError {}
+
+/// Probed stream/container info for an uploaded video, as reported by ffprobe
+#[derive(Clone, Debug, Default)]
+pub struct VideoProbe {
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub codec: Option<String>,
+    pub pix_fmt: Option<String>,
+    pub container: Option<String>,
+    pub frame_count: Option<u64>,
+    pub audio_codec: Option<String>,
+    pub frame_rate: Option<f64>,
+    pub bit_rate: Option<u64>,
+}
+
+#[derive(Deserialize)]
+struct ProbeJson {
+    #[serde(default)]
+    streams: Vec<ProbeStreamJson>,
+    #[serde(default)]
+    format: Option<ProbeFormatJson>,
+}
+
+#[derive(Deserialize)]
+struct ProbeStreamJson {
+    #[serde(default)]
+    codec_type: Option<String>,
+    #[serde(default)]
+    width: Option<u32>,
+    #[serde(default)]
+    height: Option<u32>,
+    #[serde(default)]
+    codec_name: Option<String>,
+    #[serde(default)]
+    pix_fmt: Option<String>,
+    #[serde(default)]
+    nb_read_frames: Option<String>,
+    #[serde(default)]
+    r_frame_rate: Option<String>,
+    #[serde(default)]
+    bit_rate: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ProbeFormatJson {
+    #[serde(default)]
+    format_name: Option<String>,
+    #[serde(default)]
+    bit_rate: Option<String>,
+}
+
+/// Probe a video file with ffprobe and extract stream/container info
+pub async fn probe_video<P: AsRef<Path>>(path: P) -> std::result::Result<VideoProbe, FfmpegError> {
+    let path_ref = path.as_ref();
+
+    let output = Command::new("ffprobe")
+        .arg("-v")
+        .arg("quiet")
+        .arg("-show_entries")
+        .arg("stream=codec_type,width,height,codec_name,pix_fmt,nb_read_frames,r_frame_rate,bit_rate:format=duration,format_name,bit_rate")
+        .arg("-of")
+        .arg("json")
+        .arg("-print_format")
+        .arg("json")
+        .arg(path_ref)
+        .output()
+        .await
+        .map_err(|_| FfmpegError::BinaryNotFound("ffprobe".to_string()))?;
+
+    if !output.status.success() {
+        return Err(FfmpegError::NonZeroExit {
+            command: format!("ffprobe {:?}", path_ref),
+            stderr: String::from_utf8_lossy(&output.stderr).to_string(),
+        });
+    }
+
+    let parsed: ProbeJson =
+        serde_json::from_slice(&output.stdout).map_err(|e| FfmpegError::BadJson(e.to_string()))?;
+
+    // Prefer the first video-capable stream (has width/height) for dimensions/codec
+    let video_stream = parsed.streams.iter().find(|s| s.width.is_some());
+    let audio_stream = parsed
+        .streams
+        .iter()
+        .find(|s| s.codec_type.as_deref() == Some("audio"));
+
+    let frame_rate = video_stream
+        .and_then(|s| s.r_frame_rate.as_ref())
+        .and_then(|rate| parse_frame_rate(rate));
+
+    let bit_rate = video_stream
+        .and_then(|s| s.bit_rate.as_ref())
+        .or_else(|| parsed.format.as_ref().and_then(|f| f.bit_rate.as_ref()))
+        .and_then(|s| s.parse::<u64>().ok());
+
+    Ok(VideoProbe {
+        width: video_stream.and_then(|s| s.width),
+        height: video_stream.and_then(|s| s.height),
+        codec: video_stream.and_then(|s| s.codec_name.clone()),
+        pix_fmt: video_stream.and_then(|s| s.pix_fmt.clone()),
+        container: parsed.format.and_then(|f| f.format_name),
+        frame_count: video_stream
+            .and_then(|s| s.nb_read_frames.as_ref())
+            .and_then(|s| s.parse::<u64>().ok()),
+        audio_codec: audio_stream.and_then(|s| s.codec_name.clone()),
+        frame_rate,
+        bit_rate,
+    })
+}
+
+/// Color metadata for HDR detection - separate from `VideoProbe` since most
+/// callers (upload validation, dimensions) never need it, and pulling it
+/// requires its own ffprobe `-show_entries` selection.
+#[derive(Clone, Debug, Default)]
+pub struct ColorInfo {
+    pub color_transfer: Option<String>,
+    pub color_primaries: Option<String>,
+    pub color_space: Option<String>,
+}
+
+impl ColorInfo {
+    /// PQ (`smpte2084`) or HLG (`arib-std-b67`) transfer characteristics, or
+    /// `bt2020` primaries/color space, are the ffprobe-reported markers of an
+    /// HDR (as opposed to SDR/bt709) source.
+    pub fn is_hdr(&self) -> bool {
+        let hdr_transfer = matches!(self.color_transfer.as_deref(), Some("smpte2084") | Some("arib-std-b67"));
+        let bt2020 = self.color_primaries.as_deref() == Some("bt2020")
+            || self.color_space.as_deref().map(|s| s.starts_with("bt2020")).unwrap_or(false);
+        hdr_transfer || bt2020
+    }
+}
+
+#[derive(Deserialize, Default)]
+struct ColorStreamJson {
+    #[serde(default)]
+    color_transfer: Option<String>,
+    #[serde(default)]
+    color_primaries: Option<String>,
+    #[serde(default)]
+    color_space: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ColorProbeJson {
+    #[serde(default)]
+    streams: Vec<ColorStreamJson>,
+}
+
+/// Probe `color_transfer`/`color_primaries`/`color_space` off the first video
+/// stream, for `generate_clip`'s HDR-aware encoding (see `config::HdrConfig`).
+pub async fn probe_color_info(path: &Path) -> Result<ColorInfo> {
+    let output = Command::new("ffprobe")
+        .arg("-v").arg("quiet")
+        .arg("-select_streams").arg("v:0")
+        .arg("-show_entries").arg("stream=color_transfer,color_primaries,color_space")
+        .arg("-of").arg("json")
+        .arg(path)
+        .output()
+        .await
+        .context("Failed to execute ffprobe for color info")?;
+
+    if !output.status.success() {
+        anyhow::bail!("ffprobe color probe failed: {}", String::from_utf8_lossy(&output.stderr));
+    }
+
+    let parsed: ColorProbeJson =
+        serde_json::from_slice(&output.stdout).context("Failed to parse ffprobe color JSON")?;
+    let stream = parsed.streams.into_iter().next().unwrap_or_default();
+
+    Ok(ColorInfo {
+        color_transfer: stream.color_transfer,
+        color_primaries: stream.color_primaries,
+        color_space: stream.color_space,
+    })
+}
+
+/// Whether the local ffmpeg has both `zscale` and `tonemap` compiled in -
+/// both are needed for the HDR-to-SDR filter chain `generate_clip` uses.
+/// Cached after first check, same as `detect_hardware_codec`.
+static TONEMAP_AVAILABLE: Mutex<Option<bool>> = Mutex::new(None);
+
+async fn is_tonemap_available() -> bool {
+    {
+        let cache = TONEMAP_AVAILABLE.lock().unwrap();
+        if let Some(available) = *cache {
+            return available;
+        }
+    }
+
+    let available = Command::new("ffmpeg")
+        .arg("-hide_banner")
+        .arg("-filters")
+        .output()
+        .await
+        .map(|output| {
+            let list = String::from_utf8_lossy(&output.stdout);
+            list.contains("zscale") && list.contains("tonemap")
+        })
+        .unwrap_or(false);
+
+    let mut cache = TONEMAP_AVAILABLE.lock().unwrap();
+    *cache = Some(available);
+    available
+}
+
+/// Parse ffprobe's `r_frame_rate`, reported as a rational like `"30000/1001"`
+/// rather than a plain decimal.
+fn parse_frame_rate(rate: &str) -> Option<f64> {
+    let (num, den) = rate.split_once('/')?;
+    let num: f64 = num.parse().ok()?;
+    let den: f64 = den.parse().ok()?;
+    if den == 0.0 {
+        None
+    } else {
+        Some(num / den)
+    }
+}
+
+/// Scale a clip's thread share against 1080p (1920x1080): a 4K clip gets
+/// proportionally more threads, a 480p clip gets fewer, so concurrency-based
+/// thread budgets aren't handed out as if every resolution cost the same to
+/// encode. Scaled by the square root of the pixel-count ratio (encode cost
+/// grows sub-linearly with pixel count) and clamped to a sane range.
+fn resolution_thread_weight(resolution: Option<(u32, u32)>) -> f64 {
+    const REFERENCE_PIXELS: f64 = 1920.0 * 1080.0;
+    match resolution {
+        Some((w, h)) if w > 0 && h > 0 => {
+            let pixels = w as f64 * h as f64;
+            (pixels / REFERENCE_PIXELS).sqrt().clamp(0.5, 2.0)
+        }
+        _ => 1.0,
+    }
+}
+
+/// Check the real, ffprobe-reported codec/container against the configured
+/// allow-lists, so a client that lies about `Content-Type` (or uploads
+/// something ffprobe can read frames from but we don't want to transcode)
+/// gets rejected instead of silently accepted.
+pub fn is_probe_supported(probe: &VideoProbe, config: &crate::config::ValidationConfig) -> bool {
+    let codec_ok = probe
+        .codec
+        .as_deref()
+        .map(|c| config.allowed_video_codecs.iter().any(|allowed| allowed == c))
+        .unwrap_or(false);
+
+    let container_ok = probe
+        .container
+        .as_deref()
+        .map(|container| {
+            config
+                .allowed_containers
+                .iter()
+                .any(|allowed| container.contains(allowed.as_str()))
+        })
+        .unwrap_or(false);
+
+    codec_ok && container_ok
+}
+
 /// Detect available hardware acceleration codec
 /// Returns the codec name if available, None otherwise
 /// Cached after first detection
@@ -64,6 +347,44 @@ async fn detect_hardware_codec_impl() -> Option<String> {
     }
 }
 
+/// Cached `ffmpeg -encoders` output, so validating a configured codec doesn't
+/// spawn a subprocess on every clip.
+static AVAILABLE_ENCODERS: Mutex<Option<String>> = Mutex::new(None);
+
+async fn available_encoders() -> String {
+    {
+        let cache = AVAILABLE_ENCODERS.lock().unwrap();
+        if let Some(ref encoders) = *cache {
+            return encoders.clone();
+        }
+    }
+
+    let encoders = match Command::new("ffmpeg")
+        .arg("-hide_banner")
+        .arg("-encoders")
+        .output()
+        .await
+    {
+        Ok(output) => format!(
+            "{}{}",
+            String::from_utf8_lossy(&output.stdout),
+            String::from_utf8_lossy(&output.stderr)
+        ),
+        Err(_) => String::new(),
+    };
+
+    let mut cache = AVAILABLE_ENCODERS.lock().unwrap();
+    *cache = Some(encoders.clone());
+    encoders
+}
+
+/// Check whether the installed ffmpeg build actually has the requested
+/// encoder, so users switching `default_video_codec` to e.g. `libsvtav1` get
+/// a clear error instead of ffmpeg silently rejecting `-c:v`.
+pub async fn is_codec_available(codec: &str) -> bool {
+    available_encoders().await.contains(codec)
+}
+
 /// Get video duration using ffprobe
 pub async fn get_video_duration<P: AsRef<Path>>(file_path: P) -> Result<f64> {
     let output = Command::new("ffprobe")
@@ -92,7 +413,119 @@ pub async fn get_video_duration<P: AsRef<Path>>(file_path: P) -> Result<f64> {
     Ok(duration)
 }
 
+/// Run ffmpeg's `scene` filter over a video and parse out the interior
+/// shot-change timestamps it reports - no `0.0`/duration boundaries added,
+/// just whatever `threshold` actually cleared. Shared by `detect_scene_cuts`
+/// (which adds the implicit boundaries for its documented contract) and
+/// `snap_clip_bounds` (which needs to tell "no interior cuts" apart from
+/// "cuts detected" before any boundaries are injected).
+async fn probe_interior_scene_cuts(input_path: &Path, threshold: f64) -> Result<Vec<f64>> {
+    let filter = format!("select='gt(scene,{})',metadata=print", threshold);
+    let output = Command::new("ffmpeg")
+        .arg("-i")
+        .arg(input_path)
+        .arg("-vf")
+        .arg(&filter)
+        .arg("-an")
+        .arg("-f")
+        .arg("null")
+        .arg("-")
+        .output()
+        .await
+        .context("Failed to execute ffmpeg for scene detection")?;
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let mut cuts: Vec<f64> = stderr
+        .lines()
+        .filter_map(|line| line.split_once("pts_time:").map(|(_, rest)| rest))
+        .filter_map(|v| v.split_whitespace().next()?.parse::<f64>().ok())
+        .collect();
+
+    cuts.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    cuts.dedup_by(|a, b| (*a - *b).abs() < 0.01);
+
+    Ok(cuts)
+}
+
+/// Find real shot-change timestamps in a video by running ffmpeg's built-in
+/// `scene` filter over it. Distinct from `scene::detect_scene_cuts` (a
+/// from-scratch raw-pixel-diff analysis used for batch clip planning) - this
+/// is a cheaper, single-pass check meant for snapping one clip's bounds via
+/// `snap_clip_bounds` below.
+///
+/// Always returns a sorted, deduped list with `0.0` and the source's
+/// duration present as implicit boundaries, even if no interior cuts clear
+/// `threshold`.
+pub async fn detect_scene_cuts(input_path: &Path, threshold: f64) -> Result<Vec<f64>> {
+    let duration = get_video_duration(input_path).await?;
+    let mut cuts = probe_interior_scene_cuts(input_path, threshold).await?;
+
+    cuts.push(0.0);
+    cuts.push(duration);
+    cuts.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    cuts.dedup_by(|a, b| (*a - *b).abs() < 0.01);
+
+    Ok(cuts)
+}
+
+/// Snap a single clip's `[start, start + duration)` window to real scene
+/// cuts, so it doesn't begin or end mid-shot.
+///
+/// The returned start is the nearest cut at or before the requested start;
+/// the returned end extends to the next cut, clamped so the resulting
+/// segment stays within `config.min_segment_len..=config.max_segment_len`.
+/// Falls back to the raw requested `(start, duration)` if cut detection
+/// fails, or if the video has no interior cuts at all (a static/continuous
+/// shot is the common case this needs to handle correctly, not an error) -
+/// checked against the raw interior-cut list *before* the implicit `0.0`/
+/// duration boundaries are added, since those two alone would otherwise
+/// always satisfy a "found something" check and mask this case.
+pub async fn snap_clip_bounds(
+    input_path: &Path,
+    start: f64,
+    duration: f64,
+    config: &crate::config::SceneSnapConfig,
+) -> Result<(f64, f64)> {
+    let interior_cuts = probe_interior_scene_cuts(input_path, config.threshold).await?;
+    if interior_cuts.is_empty() {
+        return Ok((start, duration));
+    }
+
+    let video_duration = get_video_duration(input_path).await?;
+    let mut cuts = interior_cuts;
+    // Collapse cuts closer than min_segment_len into the earlier one
+    cuts.dedup_by(|a, b| *a - *b < config.min_segment_len);
+    cuts.push(0.0);
+    cuts.push(video_duration);
+    cuts.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    cuts.dedup_by(|a, b| (*a - *b).abs() < 0.01);
+
+    let snapped_start = cuts
+        .iter()
+        .copied()
+        .filter(|&cut| cut <= start)
+        .next_back()
+        .unwrap_or(start);
+
+    let next_cut = cuts
+        .iter()
+        .copied()
+        .find(|&cut| cut > snapped_start + config.min_segment_len)
+        .unwrap_or(snapped_start + duration);
+
+    let max_end = snapped_start + config.max_segment_len;
+    let snapped_end = next_cut.min(max_end).max(snapped_start + config.min_segment_len);
+
+    Ok((snapped_start, snapped_end - snapped_start))
+}
+
 /// Generate a single clip from video (optimized for speed)
+///
+/// When `progress` is `Some`, the encode runs with `-progress pipe:1` and
+/// pushes a `models::EncodeProgress` update through its broadcast channel for
+/// every block ffmpeg emits, so a client can follow a single clip's own
+/// encode instead of only seeing clip-complete events. Leave it `None` for
+/// the plain fire-and-forget behavior used everywhere progress isn't wired up.
 pub async fn generate_clip(
     input_path: &Path,
     output_path: &Path,
@@ -100,32 +533,51 @@ pub async fn generate_clip(
     duration: f64,
     ffmpeg_config: &crate::config::FfmpegConfig,
     concurrent_clips: usize,
+    output_format: &crate::config::OutputFormatConfig,
+    thread_policy: &str,
+    resolution: Option<(u32, u32)>,
+    max_height: Option<u32>,
+    progress: Option<&crate::models::EncodeProgressReporter>,
 ) -> Result<()> {
     // Optimize thread allocation based on concurrent processing
     // When fewer clips run concurrently, each can use more threads
     let threads = ffmpeg_config.threads_per_clip.unwrap_or_else(|| {
-        let cpu_count = num_cpus::get();
+        let cpu_count = crate::system_info::available_parallelism();
         let advanced = ffmpeg_config.advanced.as_ref();
-        // Distribute threads more efficiently: use more threads when fewer clips run concurrently
-        let threads_per_clip = if concurrent_clips <= 2 {
-            // If only 1-2 clips, use more threads each
-            let min = advanced.map(|a| a.threads_when_1_2_clips_min).unwrap_or(2);
-            let max = advanced.map(|a| a.threads_when_1_2_clips_max).unwrap_or(6);
-            (cpu_count / concurrent_clips.max(1)).max(min).min(max)
-        } else if concurrent_clips <= 4 {
-            // If 3-4 clips, use moderate threads
-            let min = advanced.map(|a| a.threads_when_3_4_clips_min).unwrap_or(1);
-            let max = advanced.map(|a| a.threads_when_3_4_clips_max).unwrap_or(4);
-            (cpu_count / concurrent_clips.max(1)).max(min).min(max)
+
+        let threads_per_clip = if thread_policy == "dynamic_split" {
+            // Divide detected parallelism across the clips actually running right
+            // now rather than snapping to a coarse bucket - avoids oversubscription
+            // under container CPU quotas that don't match the static bucket sizes.
+            (cpu_count / concurrent_clips.max(1)).max(1)
         } else {
-            // If many clips, use fewer threads each to avoid oversubscription
-            let min = advanced.map(|a| a.threads_when_many_clips_min).unwrap_or(1);
-            let max = advanced.map(|a| a.threads_when_many_clips_max).unwrap_or(2);
-            (cpu_count / concurrent_clips.max(1)).max(min).min(max)
+            // Distribute threads more efficiently: use more threads when fewer clips run concurrently
+            if concurrent_clips <= 2 {
+                // If only 1-2 clips, use more threads each
+                let min = advanced.map(|a| a.threads_when_1_2_clips_min).unwrap_or(2);
+                let max = advanced.map(|a| a.threads_when_1_2_clips_max).unwrap_or(6);
+                (cpu_count / concurrent_clips.max(1)).max(min).min(max)
+            } else if concurrent_clips <= 4 {
+                // If 3-4 clips, use moderate threads
+                let min = advanced.map(|a| a.threads_when_3_4_clips_min).unwrap_or(1);
+                let max = advanced.map(|a| a.threads_when_3_4_clips_max).unwrap_or(4);
+                (cpu_count / concurrent_clips.max(1)).max(min).min(max)
+            } else {
+                // If many clips, use fewer threads each to avoid oversubscription
+                let min = advanced.map(|a| a.threads_when_many_clips_min).unwrap_or(1);
+                let max = advanced.map(|a| a.threads_when_many_clips_max).unwrap_or(2);
+                (cpu_count / concurrent_clips.max(1)).max(min).min(max)
+            }
         };
-        threads_per_clip
+
+        // Bias the bucket/split result by resolution: a 4K encode benefits from
+        // more threads than a 480p one even at the same concurrency level, so
+        // scale relative to 1080p rather than handing every clip an equal share.
+        let resolution_weight = resolution_thread_weight(resolution);
+        (((threads_per_clip as f64) * resolution_weight).round() as usize)
+            .clamp(1, cpu_count.max(1))
     });
-    
+
     let mut cmd = Command::new("ffmpeg");
     
     // Input seeking: use input seeking if configured (faster)
@@ -148,12 +600,27 @@ pub async fn generate_clip(
     // Duration
     cmd.arg("-t").arg(duration.to_string());
     
-    // Video codec settings - try hardware acceleration first
+    // Video codec settings
     let advanced = ffmpeg_config.advanced.as_ref();
     let default_codec = advanced.map(|a| a.default_video_codec.clone()).unwrap_or_else(|| "libx264".to_string());
-    let video_codec = detect_hardware_codec().await.unwrap_or_else(|| default_codec);
+    // Software AV1 codecs are an explicit user choice, not a fallback - don't let
+    // hardware auto-detection silently override them with h264.
+    let is_software_av1 = default_codec == "libsvtav1" || default_codec == "librav1e";
+    let video_codec = if is_software_av1 {
+        default_codec
+    } else {
+        detect_hardware_codec().await.unwrap_or(default_codec)
+    };
+
+    if !is_codec_available(&video_codec).await {
+        anyhow::bail!(
+            "Configured codec '{}' is not available in this ffmpeg build",
+            video_codec
+        );
+    }
+
     cmd.arg("-c:v").arg(&video_codec);
-    
+
     if video_codec == "libx264" {
         // CPU encoding settings
         cmd.arg("-preset").arg(&ffmpeg_config.preset);
@@ -161,6 +628,26 @@ pub async fn generate_clip(
         cmd.arg("-profile:v").arg(&ffmpeg_config.profile);
         cmd.arg("-level").arg(&ffmpeg_config.level);
         cmd.arg("-threads").arg(threads.to_string());
+    } else if video_codec == "libx265" {
+        // HEVC: same preset/crf vocabulary as x264, but profile/level naming
+        // differs enough (main/main10/...) that we don't carry those over
+        cmd.arg("-preset").arg(&ffmpeg_config.preset);
+        cmd.arg("-crf").arg(ffmpeg_config.crf.to_string());
+        cmd.arg("-threads").arg(threads.to_string());
+    } else if video_codec == "libsvtav1" {
+        // SVT-AV1: preset is 0 (slowest/best) .. 13 (fastest), crf maps directly (0-63)
+        let av1_preset = advanced.map(|a| a.av1_preset).unwrap_or(8);
+        cmd.arg("-preset").arg(av1_preset.to_string());
+        cmd.arg("-crf").arg(ffmpeg_config.crf.to_string());
+        cmd.arg("-threads").arg(threads.to_string());
+    } else if video_codec == "librav1e" {
+        // rav1e: --speed replaces preset, --quantizer replaces crf (0-255 range,
+        // roughly crf * 4 gets us into the right ballpark against x264 crf values)
+        let av1_speed = advanced.map(|a| a.av1_speed).unwrap_or(6);
+        let quantizer = (ffmpeg_config.crf as u32 * 4).min(255);
+        cmd.arg("-speed").arg(av1_speed.to_string());
+        cmd.arg("-qp").arg(quantizer.to_string());
+        cmd.arg("-threads").arg(threads.to_string());
     } else {
         // Hardware encoding settings (simpler, hardware handles most settings)
         // For VideoToolbox on macOS, use quality-based encoding
@@ -193,22 +680,33 @@ pub async fn generate_clip(
     // Performance optimizations: buffer settings for faster encoding
     // Optimized for speed over quality when processing large videos
     let advanced = ffmpeg_config.advanced.as_ref();
+    let streaming_output = output_format.container == "fmp4" || output_format.container == "hls";
+    // For fragmented/HLS output every segment must start on a keyframe, so the
+    // GOP size has to line up with segment_duration rather than the fixed
+    // defaults used for a single standalone mp4 (assumes a ~30fps source).
+    let (gop_size, keyint_min) = if streaming_output {
+        let frames_per_segment = (output_format.segment_duration * 30.0).round().max(1.0) as u32;
+        (frames_per_segment, frames_per_segment)
+    } else {
+        (
+            advanced.map(|a| a.gop_size).unwrap_or(30),
+            advanced.map(|a| a.keyint_min).unwrap_or(30),
+        )
+    };
     if let Some(adv) = advanced {
         cmd.arg("-bufsize").arg(&adv.bufsize);
         cmd.arg("-maxrate").arg(&adv.maxrate);
-        cmd.arg("-g").arg(adv.gop_size.to_string());
-        cmd.arg("-keyint_min").arg(adv.keyint_min.to_string());
     } else {
         // Fallback defaults
         cmd.arg("-bufsize").arg("1M");
         cmd.arg("-maxrate").arg("4M");
-        cmd.arg("-g").arg("30");
-        cmd.arg("-keyint_min").arg("30");
     }
+    cmd.arg("-g").arg(gop_size.to_string());
+    cmd.arg("-keyint_min").arg(keyint_min.to_string());
     
     // Tune settings - filter out unsupported options for hardware encoders
     // NVENC doesn't support "zerolatency" and "fastdecode" tune options
-    if video_codec == "libx264" {
+    if video_codec == "libx264" || video_codec == "libx265" {
         // CPU encoding supports all tune options
         for tune in &ffmpeg_config.tune {
             cmd.arg("-tune").arg(tune);
@@ -224,21 +722,111 @@ pub async fn generate_clip(
         }
     }
     
+    // HDR-aware handling: detect PQ/HLG + bt2020 input via ffprobe and either
+    // tone-map it down to an 8-bit SDR target or pass the color metadata
+    // straight through, per `ffmpeg_config.hdr.mode`. The configured mode
+    // always wins over what the source actually is - there's nothing to do
+    // for an SDR source either way.
+    let mut hdr_tonemap_filter: Option<&'static str> = None;
+    if let Some(hdr) = ffmpeg_config.hdr.as_ref().filter(|h| h.enabled) {
+        match probe_color_info(input_path).await {
+            Ok(color) if color.is_hdr() => {
+                if hdr.mode == "preserve" {
+                    if let Some(transfer) = color.color_transfer.as_deref() {
+                        cmd.arg("-color_trc").arg(transfer);
+                    }
+                    if let Some(primaries) = color.color_primaries.as_deref() {
+                        cmd.arg("-color_primaries").arg(primaries);
+                    }
+                    if let Some(space) = color.color_space.as_deref() {
+                        cmd.arg("-colorspace").arg(space);
+                    }
+                    if video_codec == "libx264" || video_codec == "libx265" {
+                        cmd.arg("-x264-params").arg("colorprim=bt2020:transfer=smpte2084:colormatrix=bt2020nc");
+                    }
+                } else if !is_tonemap_available().await {
+                    warn!("[ffmpeg] ⚠️  HDR source detected but zscale/tonemap aren't available in this ffmpeg build, encoding as-is");
+                } else {
+                    hdr_tonemap_filter = Some("zscale=t=linear:npl=100,tonemap=hable,zscale=t=bt709:m=bt709:r=tv,format=yuv420p");
+                }
+            }
+            Ok(_) => {} // SDR source - nothing to preserve or tone-map
+            Err(e) => warn!("[ffmpeg] Failed to probe color info for HDR detection, encoding as-is: {}", e),
+        }
+    }
+
+    // Downscale for this output profile's rendition (e.g. an "av1_720p"
+    // preset on a 1080p source), preserving aspect ratio via the -2 dimension.
+    // Skipped if the source is already at or below max_height - profiles don't upscale.
+    let scale_filter = if let Some(max_h) = max_height {
+        let should_scale = resolution.map(|(_, h)| h > max_h).unwrap_or(true);
+        should_scale.then(|| format!("scale=-2:{}", max_h))
+    } else {
+        None
+    };
+
+    // A single -vf chaining scale (if any) then the HDR tonemap (if any) -
+    // ffmpeg only honors the last -vf on the command line, so these can't be
+    // two separate .arg("-vf") calls.
+    let vf_chain: Vec<String> = [scale_filter, hdr_tonemap_filter.map(String::from)]
+        .into_iter()
+        .flatten()
+        .collect();
+    if !vf_chain.is_empty() {
+        cmd.arg("-vf").arg(vf_chain.join(","));
+    }
+
     // Pixel format
     cmd.arg("-pix_fmt").arg(&ffmpeg_config.pixel_format);
     
-    // Audio codec
-    cmd.arg("-c:a").arg(&ffmpeg_config.audio_codec);
+    // Audio codec and optional channel isolation / loudness normalization.
+    // Both of those need an audio filter, which means we can't stream-copy -
+    // they force a re-encode with AudioConfig::codec (or a sensible default).
+    let audio_filters: Vec<String> = match &ffmpeg_config.audio {
+        Some(audio) => {
+            let mut filters = Vec::new();
+            if let Some(channel) = audio.channel_map {
+                filters.push(format!("pan=mono|c0=c{}", channel));
+            }
+            if audio.normalize {
+                filters.push(format!("loudnorm=I={}:TP=-1.5:LRA=11", audio.target_lufs));
+            }
+            filters
+        }
+        None => Vec::new(),
+    };
+
+    let audio_codec_set = ffmpeg_config.audio.as_ref().map_or(false, |a| a.codec.is_some());
+    if !audio_filters.is_empty() || audio_codec_set {
+        let codec = ffmpeg_config
+            .audio
+            .as_ref()
+            .and_then(|a| a.codec.clone())
+            .unwrap_or_else(|| "aac".to_string());
+        cmd.arg("-c:a").arg(&codec);
+        if let Some(audio) = ffmpeg_config.audio.as_ref() {
+            cmd.arg("-b:a").arg(&audio.bitrate);
+        }
+        if !audio_filters.is_empty() {
+            cmd.arg("-af").arg(audio_filters.join(","));
+        }
+    } else {
+        cmd.arg("-c:a").arg(&ffmpeg_config.audio_codec);
+    }
     
     // Additional flags - handle movflags and fflags specially
     let mut movflags = Vec::new();
     let mut fflags = Vec::new();
     let mut other_flags = Vec::new();
     
+    if ffmpeg_config.faststart {
+        movflags.push("faststart");
+    }
+
     for flag in &ffmpeg_config.additional_flags {
         if flag.starts_with("+") {
-            // Flags like +faststart go to movflags
-            movflags.push(flag.as_str());
+            // Any other +movflag entries (besides faststart, which is its own field)
+            movflags.push(flag.trim_start_matches('+'));
         } else if flag.starts_with("fflags=") {
             // fflags like fflags=+genpts
             let fflag_value = flag.strip_prefix("fflags=").unwrap_or(flag);
@@ -258,8 +846,12 @@ pub async fn generate_clip(
         }
     }
     
-    // Add movflags if any (combine with +)
-    if !movflags.is_empty() {
+    // Add movflags if any (combine with +). Fragmented/HLS output replaces
+    // +faststart (which needs a seekable moov atom written after encoding)
+    // with frag_keyframe+empty_moov so the file can be muxed as fragments.
+    if streaming_output {
+        cmd.arg("-movflags").arg("frag_keyframe+empty_moov");
+    } else if !movflags.is_empty() {
         cmd.arg("-movflags").arg(movflags.join("+"));
     }
     
@@ -277,25 +869,293 @@ pub async fn generate_clip(
         }
     }
     
+    if progress.is_some() {
+        cmd.arg("-progress").arg("pipe:1").arg("-nostats");
+    }
+
     // Overwrite output
     cmd.arg("-y").arg(output_path);
-    
-    // Suppress output
-    let output = cmd
+
+    let mut child = cmd
+        .stdout(if progress.is_some() { Stdio::piped() } else { Stdio::null() })
+        .stderr(Stdio::piped())
+        .spawn()
+        .context("Failed to spawn ffmpeg")?;
+
+    // Stderr has to be drained concurrently with stdout (both capped pipe
+    // buffers) or a chatty one can deadlock ffmpeg once its pipe fills up.
+    let mut stderr_pipe = child.stderr.take().context("ffmpeg stderr not piped")?;
+    let stderr_task = async move {
+        let mut buf = Vec::new();
+        let _ = stderr_pipe.read_to_end(&mut buf).await;
+        buf
+    };
+
+    let stdout_pipe = child.stdout.take();
+    let progress_task = async move {
+        let (Some(reporter), Some(stdout_pipe)) = (progress, stdout_pipe) else {
+            return;
+        };
+        report_progress(stdout_pipe, reporter).await;
+    };
+
+    let (stderr_bytes, ()) = tokio::join!(stderr_task, progress_task);
+    let status = child.wait().await.context("Failed waiting on ffmpeg")?;
+
+    if !status.success() {
+        let stderr = String::from_utf8_lossy(&stderr_bytes);
+        // Command's Debug impl prints the full program + args, which `broker`
+        // surfaces verbatim once retries are exhausted.
+        anyhow::bail!("ffmpeg failed (cmd: {:?}): {}", cmd.as_std(), stderr);
+    }
+
+    Ok(())
+}
+
+/// Encode a clip straight off a byte stream fed through ffmpeg's stdin
+/// (`-i pipe:0`) instead of an on-disk path, for the eager-preview ingestion
+/// path in `upload_handler` (see `config::StreamingIngestConfig`) where the
+/// upload hasn't finished landing on disk yet.
+///
+/// Input seeking is unreliable against a pipe (ffmpeg can't seek backwards
+/// once bytes are consumed), so this always starts at the beginning of the
+/// stream and only supports an output-side `-t duration` cut - there is no
+/// `start_time`/`-ss` here at all, unlike `generate_clip`. `use_input_seeking`
+/// is ignored entirely in this mode. Callers that need an arbitrary
+/// mid-stream segment should use the file-based `generate_clip` instead,
+/// which remains the default path.
+///
+/// Reuses `detect_hardware_codec` and the preset/CRF vocabulary from
+/// `generate_clip`, but - being a best-effort preview rather than a final
+/// delivery rendition - skips the software-AV1, per-profile downscaling and
+/// audio-filter branches `generate_clip` also has.
+pub async fn generate_clip_from_stdin(
+    mut input_rx: tokio::sync::mpsc::Receiver<axum::body::Bytes>,
+    output_path: &Path,
+    duration: f64,
+    ffmpeg_config: &crate::config::FfmpegConfig,
+) -> Result<()> {
+    let advanced = ffmpeg_config.advanced.as_ref();
+    let default_codec = advanced.map(|a| a.default_video_codec.clone()).unwrap_or_else(|| "libx264".to_string());
+    let video_codec = detect_hardware_codec().await.unwrap_or(default_codec);
+
+    if !is_codec_available(&video_codec).await {
+        anyhow::bail!(
+            "Configured codec '{}' is not available in this ffmpeg build",
+            video_codec
+        );
+    }
+
+    let mut cmd = Command::new("ffmpeg");
+    cmd.arg("-i").arg("pipe:0");
+    cmd.arg("-t").arg(duration.to_string());
+    cmd.arg("-c:v").arg(&video_codec);
+
+    if video_codec == "libx264" || video_codec == "libx265" {
+        cmd.arg("-preset").arg(&ffmpeg_config.preset);
+        cmd.arg("-crf").arg(ffmpeg_config.crf.to_string());
+    } else if let Some(adv) = advanced {
+        if video_codec == "h264_videotoolbox" {
+            let quality = (adv.videotoolbox_quality_max as f64 - (ffmpeg_config.crf as f64 * adv.videotoolbox_crf_multiplier))
+                .max(adv.videotoolbox_quality_min as f64)
+                .min(adv.videotoolbox_quality_max as f64) as u8;
+            cmd.arg("-quality").arg(quality.to_string());
+            cmd.arg("-allow_sw").arg("1");
+        } else if video_codec == "h264_nvenc" {
+            cmd.arg("-preset").arg(&adv.nvenc_preset);
+            cmd.arg("-rc").arg(&adv.nvenc_rc);
+            cmd.arg("-cq").arg(ffmpeg_config.crf.to_string());
+        } else if video_codec == "h264_qsv" {
+            cmd.arg("-preset").arg(&adv.qsv_preset);
+            cmd.arg("-global_quality").arg(ffmpeg_config.crf.to_string());
+        } else if video_codec == "h264_amf" {
+            cmd.arg("-quality").arg(&adv.amf_quality);
+            cmd.arg("-rc").arg(&adv.amf_rc);
+        }
+    }
+
+    cmd.arg("-pix_fmt").arg(&ffmpeg_config.pixel_format);
+    cmd.arg("-c:a").arg(&ffmpeg_config.audio_codec);
+    // A pipe has no seekable moov atom to rewrite after the fact, so
+    // +faststart is meaningless here - fragment instead, same as the
+    // fmp4/hls streaming_output case in generate_clip.
+    cmd.arg("-movflags").arg("frag_keyframe+empty_moov");
+    cmd.arg("-y").arg(output_path);
+
+    let mut child = cmd
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .context("Failed to spawn ffmpeg")?;
+
+    let mut stdin_pipe = child.stdin.take().context("ffmpeg stdin not piped")?;
+    let stdin_task = async move {
+        while let Some(chunk) = input_rx.recv().await {
+            if stdin_pipe.write_all(&chunk).await.is_err() {
+                break;
+            }
+        }
+        // Dropping the handle closes the pipe, which is how ffmpeg sees EOF
+        // on its stdin and finalizes the encode.
+        drop(stdin_pipe);
+    };
+
+    let mut stderr_pipe = child.stderr.take().context("ffmpeg stderr not piped")?;
+    let stderr_task = async move {
+        let mut buf = Vec::new();
+        let _ = stderr_pipe.read_to_end(&mut buf).await;
+        buf
+    };
+
+    let (stderr_bytes, ()) = tokio::join!(stderr_task, stdin_task);
+    let status = child.wait().await.context("Failed waiting on ffmpeg")?;
+
+    if !status.success() {
+        let stderr = String::from_utf8_lossy(&stderr_bytes);
+        anyhow::bail!("ffmpeg failed (cmd: {:?}): {}", cmd.as_std(), stderr);
+    }
+
+    Ok(())
+}
+
+/// Parse ffmpeg's `-progress pipe:1` `key=value` blocks (one block per
+/// `progress=continue`/`progress=end` line) off `stdout_pipe` and push an
+/// `EncodeProgress` through `reporter.tx` for each one. Sends are
+/// best-effort - nobody listening is not an error.
+async fn report_progress(stdout_pipe: tokio::process::ChildStdout, reporter: &crate::models::EncodeProgressReporter) {
+    let mut lines = tokio::io::BufReader::new(stdout_pipe).lines();
+    let mut out_time_secs: f64 = 0.0;
+    let mut fps: f64 = 0.0;
+    let mut speed: f64 = 0.0;
+
+    loop {
+        let line = match lines.next_line().await {
+            Ok(Some(line)) => line,
+            _ => break,
+        };
+        let Some((key, value)) = line.split_once('=') else { continue };
+        let value = value.trim();
+        match key {
+            "out_time_us" => {
+                if let Ok(us) = value.parse::<f64>() {
+                    out_time_secs = (us / 1_000_000.0).max(0.0);
+                }
+            }
+            "fps" => fps = value.parse().unwrap_or(fps),
+            "speed" => speed = value.trim_end_matches('x').parse().unwrap_or(speed),
+            "progress" => {
+                let percent = if reporter.total_duration > 0.0 {
+                    (out_time_secs / reporter.total_duration * 100.0).clamp(0.0, 100.0)
+                } else {
+                    0.0
+                };
+                let remaining = (reporter.total_duration - out_time_secs).max(0.0);
+                let eta_seconds = if speed > 0.0 { remaining / speed } else { 0.0 };
+
+                let _ = reporter.tx.send(crate::models::EncodeProgress {
+                    video_id: reporter.video_id.clone(),
+                    clip_id: reporter.clip_id.clone(),
+                    percent,
+                    fps,
+                    speed,
+                    eta_seconds,
+                });
+
+                if value == "end" {
+                    break;
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Namespace a configured playlist filename (e.g. `playlist.m3u8`) with the
+/// clip id (e.g. `clip-1-playlist.m3u8`) so multiple clips from one video
+/// don't clobber each other's playlist in a shared output directory.
+pub fn playlist_file_name(clip_id: &str, playlist_name: &str) -> String {
+    format!("{}-{}", clip_id, playlist_name)
+}
+
+/// Segment an already-encoded fragmented-mp4 clip into an HLS playlist
+/// (fragmented-mp4 segments + `.m3u8`), stream-copying so no re-encode is
+/// needed. Segments and the playlist are written alongside `clip_path`.
+pub async fn mux_hls(
+    clip_path: &Path,
+    clip_id: &str,
+    output_format: &crate::config::OutputFormatConfig,
+) -> Result<()> {
+    let dir = clip_path
+        .parent()
+        .context("Clip path has no parent directory")?;
+    // Namespace the playlist per clip - a video can produce several clips
+    // sharing this output directory, and they'd otherwise overwrite each
+    // other's playlist_name.
+    let playlist_path = dir.join(playlist_file_name(clip_id, &output_format.playlist_name));
+    let segment_pattern = dir.join(format!("{}-%03d.m4s", clip_id));
+    let init_segment = dir.join(format!("{}-init.mp4", clip_id));
+
+    let output = Command::new("ffmpeg")
+        .arg("-i").arg(clip_path)
+        .arg("-c").arg("copy")
+        .arg("-f").arg("hls")
+        .arg("-hls_time").arg(output_format.segment_duration.to_string())
+        .arg("-hls_playlist_type").arg("vod")
+        .arg("-hls_segment_type").arg("fmp4")
+        .arg("-hls_fmp4_init_filename").arg(init_segment.file_name().unwrap())
+        .arg("-hls_segment_filename").arg(&segment_pattern)
+        .arg("-y").arg(&playlist_path)
         .stdout(Stdio::null())
         .stderr(Stdio::piped())
         .output()
         .await
-        .context("Failed to execute ffmpeg")?;
+        .context("Failed to execute ffmpeg for HLS segmenting")?;
 
     if !output.status.success() {
         let stderr = String::from_utf8_lossy(&output.stderr);
-        anyhow::bail!("ffmpeg failed: {}", stderr);
+        anyhow::bail!("ffmpeg HLS segmenting failed: {}", stderr);
     }
 
     Ok(())
 }
 
+/// Strip all container/stream metadata (GPS, device info, creation timestamps,
+/// embedded cover art) from a generated clip so it isn't leaked to viewers.
+/// Stream-copies to a sibling temp file, then renames it over the original.
+pub async fn strip_metadata(clip_path: &Path) -> Result<()> {
+    let temp_path = clip_path.with_extension("stripped.mp4");
+
+    let output = Command::new("ffmpeg")
+        .arg("-i")
+        .arg(clip_path)
+        .arg("-map_metadata")
+        .arg("-1")
+        .arg("-map_chapters")
+        .arg("-1")
+        .arg("-c")
+        .arg("copy")
+        .arg("-y")
+        .arg(&temp_path)
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .output()
+        .await
+        .context("Failed to execute ffmpeg for metadata stripping")?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let _ = tokio::fs::remove_file(&temp_path).await;
+        anyhow::bail!("ffmpeg metadata stripping failed: {}", stderr);
+    }
+
+    tokio::fs::rename(&temp_path, clip_path)
+        .await
+        .context("Failed to replace clip with metadata-stripped copy")?;
+
+    Ok(())
+}
+
 /// Generate a thumbnail image from a video clip
 /// Extracts a frame at the specified time (default 0.2s to avoid black frames)
 pub async fn generate_thumbnail(
@@ -339,6 +1199,126 @@ pub async fn generate_thumbnail(
     Ok(())
 }
 
+/// Candidate timestamps sampled across a clip when choosing the best-looking
+/// thumbnail frame
+const THUMBNAIL_CANDIDATES: usize = 5;
+/// Side length each candidate frame is downscaled to for cheap scoring
+const THUMBNAIL_SCORE_GRID: u32 = 64;
+
+/// Sample several candidate frames across the clip and score each by
+/// sharpness (variance of the Laplacian on a downscaled grayscale frame),
+/// penalizing near-uniform frames (catches black/fade frames) and rewarding a
+/// mildly center-weighted colorfulness term, then return the timestamp of the
+/// highest-scoring candidate. Falls back to `fallback_time` if every
+/// candidate frame failed to extract or score.
+pub async fn select_best_thumbnail_frame(video_path: &Path, clip_duration: f64, fallback_time: f64) -> f64 {
+    let mut best: Option<(f64, f64)> = None; // (score, time)
+
+    for i in 0..THUMBNAIL_CANDIDATES {
+        // Spread candidates across the middle 80% of the clip, avoiding the
+        // very first/last frames where cuts and fades are most common
+        let t = clip_duration * (0.1 + 0.8 * (i as f64 + 0.5) / THUMBNAIL_CANDIDATES as f64);
+        match score_thumbnail_candidate(video_path, t).await {
+            Ok(score) => {
+                if best.map_or(true, |(best_score, _)| score > best_score) {
+                    best = Some((score, t));
+                }
+            }
+            Err(e) => {
+                warn!("[thumbnail] Failed to score candidate frame at {:.2}s: {}", t, e);
+            }
+        }
+    }
+
+    best.map(|(_, t)| t).unwrap_or(fallback_time)
+}
+
+/// Score a single candidate frame: sharpness (Laplacian variance) minus a
+/// flatness penalty (catches black/fade frames) plus a mild center-weighted
+/// colorfulness term.
+async fn score_thumbnail_candidate(video_path: &Path, time: f64) -> Result<f64> {
+    let output = Command::new("ffmpeg")
+        .arg("-ss").arg(time.to_string())
+        .arg("-i").arg(video_path)
+        .arg("-vframes").arg("1")
+        .arg("-vf").arg(format!(
+            "scale={}:{}:flags=area,format=rgb24",
+            THUMBNAIL_SCORE_GRID, THUMBNAIL_SCORE_GRID
+        ))
+        .arg("-f").arg("rawvideo")
+        .arg("-")
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .output()
+        .await
+        .context("Failed to execute ffmpeg for thumbnail candidate scoring")?;
+
+    let side = THUMBNAIL_SCORE_GRID as usize;
+    let pixel_count = side * side;
+    if !output.status.success() || output.stdout.len() < pixel_count * 3 {
+        anyhow::bail!("ffmpeg did not produce an rgb frame at {:.2}s for thumbnail scoring", time);
+    }
+    let rgb = &output.stdout[..pixel_count * 3];
+
+    let luminance: Vec<f64> = (0..pixel_count)
+        .map(|i| {
+            let r = rgb[i * 3] as f64;
+            let g = rgb[i * 3 + 1] as f64;
+            let b = rgb[i * 3 + 2] as f64;
+            0.299 * r + 0.587 * g + 0.114 * b
+        })
+        .collect();
+
+    let lum_mean = luminance.iter().sum::<f64>() / pixel_count as f64;
+    let lum_variance = luminance.iter().map(|l| (l - lum_mean).powi(2)).sum::<f64>() / pixel_count as f64;
+
+    // Laplacian variance over interior pixels - a standard no-reference
+    // sharpness proxy: blurry/flat frames produce small second derivatives.
+    let mut laplacians = Vec::with_capacity((side - 2) * (side - 2));
+    for y in 1..side - 1 {
+        for x in 1..side - 1 {
+            let center = luminance[y * side + x];
+            let up = luminance[(y - 1) * side + x];
+            let down = luminance[(y + 1) * side + x];
+            let left = luminance[y * side + x - 1];
+            let right = luminance[y * side + x + 1];
+            laplacians.push(up + down + left + right - 4.0 * center);
+        }
+    }
+    let lap_mean = laplacians.iter().sum::<f64>() / laplacians.len() as f64;
+    let sharpness = laplacians.iter().map(|l| (l - lap_mean).powi(2)).sum::<f64>() / laplacians.len() as f64;
+
+    // Penalize near-uniform frames (black frames, fades) whose overall pixel
+    // variance is tiny regardless of how "sharp" their noise floor looks.
+    const UNIFORM_VARIANCE_FLOOR: f64 = 50.0;
+    let flatness_penalty = (UNIFORM_VARIANCE_FLOOR - lum_variance).max(0.0) * 10.0;
+
+    // Mild center-weighted colorfulness: average per-channel spread, weighted
+    // higher for pixels nearer the frame center so a colorful subject counts
+    // more than colorful noise at the edges.
+    let center = (side as f64 - 1.0) / 2.0;
+    let max_dist = (center * center * 2.0).sqrt();
+    let mut colorfulness = 0.0;
+    let mut weight_sum = 0.0;
+    for y in 0..side {
+        for x in 0..side {
+            let i = y * side + x;
+            let r = rgb[i * 3] as f64;
+            let g = rgb[i * 3 + 1] as f64;
+            let b = rgb[i * 3 + 2] as f64;
+            let spread = (r - g).abs() + (g - b).abs() + (b - r).abs();
+            let dist = (((x as f64 - center).powi(2) + (y as f64 - center).powi(2)).sqrt()) / max_dist;
+            let weight = 1.0 - dist * 0.5;
+            colorfulness += spread * weight;
+            weight_sum += weight;
+        }
+    }
+    colorfulness /= weight_sum;
+
+    Ok(sharpness - flatness_penalty + 0.1 * colorfulness)
+}
+
 /// Check if ffmpeg is available
 #[allow(dead_code)]
 pub async fn check_ffmpeg_available() -> Result<String> {
@@ -1,20 +1,26 @@
 use crate::{
+    broker,
     config::Config,
+    dedup,
     ffmpeg,
-    models::{Clip, ClipRequest, ClipResponse, ConfigResponse, ErrorResponse, SystemInfoResponse, UploadResponse, VideoMetadata},
+    models::{Clip, ClipRequest, ClipResponse, ConfigResponse, DuplicateMatch, DuplicatesResponse, ErrorResponse, SystemInfoResponse, UploadResponse, VideoMetadata},
+    publish,
+    scene,
     system_info,
+    vmaf,
 };
 use axum::{
-    extract::{Multipart, State},
-    http::StatusCode,
-    response::Json,
+    body::Body,
+    extract::{Multipart, Path as AxumPath, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{sse::Sse, IntoResponse, Json, Response},
 };
 use std::{
     path::PathBuf,
     sync::Arc,
     time::SystemTime,
 };
-use tokio::io::AsyncWriteExt;
+use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
 use tracing::{error, info, warn};
 use uuid::Uuid;
 
@@ -25,6 +31,14 @@ pub async fn upload_handler(
     State(state): State<Arc<AppState>>,
     mut multipart: Multipart,
 ) -> Result<Json<UploadResponse>, (StatusCode, Json<ErrorResponse>)> {
+    if let Err(reason) = system_info::check_admission(&state.config) {
+        warn!("[POST /upload] Rejecting upload: {}", reason);
+        return Err((
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(ErrorResponse { error: reason }),
+        ));
+    }
+
     let start_time = SystemTime::now();
     let mem_before = system_info::get_memory_usage();
 
@@ -96,7 +110,37 @@ pub async fn upload_handler(
             // Stream chunks to file using buffered writes for better performance
             let mut chunk_count = 0;
             let mut buffer = Vec::with_capacity(state.config.upload_buffer_size); // Use configured buffer size
-            
+
+            // Pipe-based eager preview: tee the same bytes into an ffmpeg
+            // child over stdin as they arrive, so a short preview clip is
+            // ready without a separate re-read of `path` once the upload
+            // finishes (see `config::StreamingIngestConfig`). Best-effort -
+            // any failure here only drops the preview, never the upload.
+            let preview_ingest = if state.config.streaming_ingest.enabled {
+                let inflight = state.streaming_ingest_inflight.clone();
+                if inflight.fetch_add(1, std::sync::atomic::Ordering::SeqCst)
+                    < state.config.streaming_ingest.max_concurrent
+                {
+                    let (tx, rx) = tokio::sync::mpsc::channel::<axum::body::Bytes>(32);
+                    let preview_path = state.config.output_dir.join(format!("{}-preview.mp4", video_id));
+                    let ffmpeg_config = state.config.ffmpeg.clone();
+                    let preview_len = state.config.streaming_ingest.preview_clip_length;
+                    let task = tokio::spawn(async move {
+                        ffmpeg::generate_clip_from_stdin(rx, &preview_path, preview_len, &ffmpeg_config).await
+                    });
+                    Some((tx, task, inflight))
+                } else {
+                    // Already at the pipe-ingest concurrency cap - skip the
+                    // preview for this upload rather than spawn another
+                    // ffmpeg child on top of it.
+                    inflight.fetch_sub(1, std::sync::atomic::Ordering::SeqCst);
+                    None
+                }
+            } else {
+                None
+            };
+            let mut preview_tx = preview_ingest.as_ref().map(|(tx, _, _)| tx.clone());
+
             loop {
                 let chunk = match field.chunk().await {
                     Ok(Some(chunk)) => chunk,
@@ -152,7 +196,16 @@ pub async fn upload_handler(
 
                 // Buffer writes for better I/O performance
                 buffer.extend_from_slice(&chunk);
-                
+
+                // Forward the same chunk to the eager-preview ffmpeg child, if
+                // one is running. A send failure means its encode already
+                // finished or died - stop feeding it, the upload continues.
+                if let Some(tx) = &preview_tx {
+                    if tx.send(chunk.clone()).await.is_err() {
+                        preview_tx = None;
+                    }
+                }
+
                 // Flush buffer when it reaches configured size
                 if buffer.len() >= state.config.upload_buffer_size {
                     file.write_all(&buffer).await.map_err(|e| {
@@ -177,6 +230,21 @@ pub async fn upload_handler(
                 }
             }
 
+            // Close the preview child's stdin (dropping both senders) and
+            // wait for its encode to wrap up now that every chunk has been
+            // forwarded; any outcome here is logged, never surfaced as an
+            // upload failure.
+            if let Some((tx, task, inflight)) = preview_ingest {
+                drop(tx);
+                drop(preview_tx);
+                match task.await {
+                    Ok(Ok(())) => info!("[POST /upload] ✅ Eager preview clip ready for video {}", video_id),
+                    Ok(Err(e)) => warn!("[POST /upload] Eager preview clip failed for video {}: {}", video_id, e),
+                    Err(e) => warn!("[POST /upload] Eager preview task panicked for video {}: {}", video_id, e),
+                }
+                inflight.fetch_sub(1, std::sync::atomic::Ordering::SeqCst);
+            }
+
             file.sync_all().await.map_err(|e| {
                 error!("[POST /upload] Failed to sync file: {}", e);
                 (
@@ -244,6 +312,92 @@ pub async fn upload_handler(
     let duration_time = duration_start.elapsed().unwrap().as_secs_f64();
     info!("[POST /upload] ‚úÖ Video duration: {:.2}s (detected in {:.2}s)", duration, duration_time);
 
+    // Probe real stream/container info so we validate the upload is actually decodable
+    // video rather than trusting the client's Content-Type
+    let probe = match ffmpeg::probe_video(&file_path).await {
+        Ok(probe) => {
+            info!(
+                "[POST /upload] ‚úÖ Probed: {}x{} {} ({})",
+                probe.width.unwrap_or(0),
+                probe.height.unwrap_or(0),
+                probe.codec.as_deref().unwrap_or("unknown codec"),
+                probe.container.as_deref().unwrap_or("unknown container")
+            );
+            probe
+        }
+        Err(e) => {
+            warn!("[POST /upload] Metadata probe failed, proceeding without it: {}", e);
+            ffmpeg::VideoProbe::default()
+        }
+    };
+
+    if probe.width.is_none() {
+        let _ = tokio::fs::remove_file(&file_path).await;
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                error: "Uploaded file is not a decodable video".to_string(),
+            }),
+        ));
+    }
+
+    // Validate the real, probed codec/container rather than trusting the
+    // multipart Content-Type, which a client can set to anything.
+    if !ffmpeg::is_probe_supported(&probe, &state.config.validation) {
+        warn!(
+            "[POST /upload] ‚ùå Rejected unsupported format: codec={:?} container={:?}",
+            probe.codec, probe.container
+        );
+        let _ = tokio::fs::remove_file(&file_path).await;
+        return Err((
+            StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            Json(ErrorResponse {
+                error: format!(
+                    "Unsupported video format (codec={}, container={})",
+                    probe.codec.as_deref().unwrap_or("unknown"),
+                    probe.container.as_deref().unwrap_or("unknown")
+                ),
+            }),
+        ));
+    }
+
+    // Perceptual-hash dedup: if this upload matches an already-ingested video
+    // within tolerance, reuse its video_id (and any clips already cached for it)
+    // instead of storing and re-processing a duplicate.
+    let fingerprint = if state.config.dedup.enabled {
+        match dedup::compute_fingerprint(&file_path, duration).await {
+            Ok(fp) => Some(fp),
+            Err(e) => {
+                warn!("[POST /upload] ⚠️  Failed to compute dedup fingerprint: {}", e);
+                None
+            }
+        }
+    } else {
+        None
+    };
+
+    if let Some(fp) = &fingerprint {
+        let tolerance = dedup::total_tolerance(state.config.dedup.tolerance_bits_per_frame);
+        let existing_id = state.dedup_tree.read().await.find_within(fp, tolerance);
+        if let Some(existing_id) = existing_id {
+            info!(
+                "[POST /upload] ✅ Duplicate of video {} detected, reusing it instead of re-processing",
+                existing_id
+            );
+            let _ = tokio::fs::remove_file(&file_path).await;
+            let existing = state.videos.read().await.get(&existing_id).cloned();
+            return Ok(Json(UploadResponse {
+                video_id: existing_id,
+                width: existing.as_ref().and_then(|v| v.width),
+                height: existing.as_ref().and_then(|v| v.height),
+                codec: existing.as_ref().and_then(|v| v.codec.clone()),
+                audio_codec: existing.as_ref().and_then(|v| v.audio_codec.clone()),
+                frame_rate: existing.as_ref().and_then(|v| v.frame_rate),
+                bit_rate: existing.and_then(|v| v.bit_rate),
+            }));
+        }
+    }
+
     // Store video metadata
     let video_metadata = VideoMetadata {
         id: video_id.clone(),
@@ -252,8 +406,26 @@ pub async fn upload_handler(
         original_name: original_name.clone(),
         file_size,
         uploaded_at: SystemTime::now(),
+        width: probe.width,
+        height: probe.height,
+        codec: probe.codec.clone(),
+        pix_fmt: probe.pix_fmt.clone(),
+        container: probe.container.clone(),
+        frame_count: probe.frame_count,
+        audio_codec: probe.audio_codec.clone(),
+        frame_rate: probe.frame_rate,
+        bit_rate: probe.bit_rate,
+        fingerprint: fingerprint.clone(),
     };
 
+    if let Some(fp) = fingerprint {
+        state.dedup_tree.write().await.insert(video_id.clone(), fp);
+    }
+
+    if let Err(e) = state.db.upsert_video(&video_metadata).await {
+        warn!("[POST /upload] ⚠️  Failed to persist video metadata to db: {}", e);
+    }
+
     state
         .videos
         .write()
@@ -277,7 +449,15 @@ pub async fn upload_handler(
         mem_delta
     );
 
-    Ok(Json(UploadResponse { video_id }))
+    Ok(Json(UploadResponse {
+        video_id,
+        width: probe.width,
+        height: probe.height,
+        codec: probe.codec,
+        audio_codec: probe.audio_codec,
+        frame_rate: probe.frame_rate,
+        bit_rate: probe.bit_rate,
+    }))
 }
 
 /// Root route handler - returns API information
@@ -291,7 +471,8 @@ pub async fn root_handler() -> Json<serde_json::Value> {
             "GET /config": "Get backend configuration and system limits",
             "POST /upload": "Upload a video file",
             "POST /clip": "Generate clips from an uploaded video",
-            "GET /clips/*": "Serve generated clip files"
+            "GET /clips/*": "Serve generated clip files",
+            "GET /debug/state": "Operator diagnostics dump (disabled by default)"
         }
     }))
 }
@@ -301,16 +482,27 @@ pub async fn config_handler(
     State(state): State<Arc<AppState>>,
 ) -> Json<ConfigResponse> {
     let sys_info = system_info::get_system_info();
-    
+    let mem_usage = system_info::get_memory_usage();
+
     // Calculate safe number of concurrent videos
     // Each video can generate multiple clips, so we need to be conservative
-    // Formula: max(1, min(3, max_concurrent_clips / 3))
-    // This ensures we don't overload the system while allowing some parallelism
+    // Formula: max(1, min(3, max_concurrent_clips / 3)), then scaled down as free
+    // memory approaches the admission-control high-water mark so clients back off
+    // before the server starts rejecting requests outright
     let max_concurrent_videos = {
         let calculated = (state.config.max_concurrent_clips as f64 / 3.0).ceil() as usize;
-        calculated.max(1).min(3)
+        let base = calculated.max(1).min(3);
+
+        let headroom_gb = sys_info.memory_free_gb - state.config.admission.min_free_memory_gb;
+        if headroom_gb <= 0.0 {
+            1
+        } else if headroom_gb < state.config.admission.min_free_memory_gb {
+            base.max(1).min(2)
+        } else {
+            base
+        }
     };
-    
+
     Json(ConfigResponse {
         max_concurrent_clips: state.config.max_concurrent_clips,
         max_file_size: state.config.max_file_size,
@@ -319,122 +511,202 @@ pub async fn config_handler(
             cpus: sys_info.cpus,
             memory_free_gb: sys_info.memory_free_gb,
             memory_total_gb: sys_info.memory_total_gb,
+            process_rss_mb: mem_usage.rss_mb,
         },
     })
 }
 
+/// Dump live server state (in-memory video metadata, a checksummed walk of
+/// disk, and system info) for operators debugging leaks or cleanup bugs.
+/// Gated behind config so it's never exposed unless an operator opts in.
+pub async fn debug_state_handler(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<crate::diagnostics::StateDump>, StatusCode> {
+    if !state.config.diagnostics.enable_state_dump {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    Ok(Json(crate::diagnostics::dump_state(&state).await))
+}
+
+/// List videos whose perceptual fingerprint is within the configured dedup
+/// tolerance of `video_id`'s, nearest first. Distances are normalized to
+/// 0.0-1.0 (Hamming distance over total fingerprint bit length) so clients
+/// don't need to know the fingerprint's internal size.
+pub async fn duplicates_handler(
+    State(state): State<Arc<AppState>>,
+    AxumPath(video_id): AxumPath<String>,
+) -> Result<Json<DuplicatesResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let fingerprint = match state.videos.read().await.get(&video_id).cloned() {
+        Some(video) => video.fingerprint,
+        None => match state.db.get_video(&video_id).await {
+            Ok(Some(video)) => video.fingerprint,
+            Ok(None) => None,
+            Err(e) => {
+                return Err((
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(ErrorResponse { error: format!("Failed to query metadata store: {}", e) }),
+                ));
+            }
+        },
+    };
+
+    let Some(fingerprint) = fingerprint else {
+        return Err((
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse { error: "Video not found or has no fingerprint".to_string() }),
+        ));
+    };
+
+    let tolerance = dedup::total_tolerance(state.config.dedup.tolerance_bits_per_frame);
+    let bit_length = (fingerprint.len() * 8) as f64;
+    let duplicates = state
+        .dedup_tree
+        .read()
+        .await
+        .find_all_within(&fingerprint, tolerance)
+        .into_iter()
+        .filter(|(id, _)| *id != video_id)
+        .map(|(id, dist)| DuplicateMatch { video_id: id, distance: dist as f64 / bit_length })
+        .collect();
+
+    Ok(Json(DuplicatesResponse { video_id, duplicates }))
+}
+
+/// Stream live `generateClips` progress for `video_id` as Server-Sent Events,
+/// one JSON-encoded `ClipProgress` per encoded clip, so a frontend can show a
+/// progress bar instead of just waiting on the `/clip` response.
+pub async fn progress_handler(
+    State(state): State<Arc<AppState>>,
+    AxumPath(video_id): AxumPath<String>,
+) -> Sse<impl futures_util::Stream<Item = Result<axum::response::sse::Event, std::convert::Infallible>>> {
+    use axum::response::sse::Event;
+    use tokio_stream::wrappers::BroadcastStream;
+    use futures_util::StreamExt;
+
+    let rx = state.progress_tx.subscribe();
+    let stream = BroadcastStream::new(rx).filter_map(move |msg| {
+        let video_id = video_id.clone();
+        async move {
+            match msg {
+                Ok(progress) if progress.video_id == video_id => {
+                    let json = serde_json::to_string(&progress).ok()?;
+                    Some(Ok(Event::default().data(json)))
+                }
+                Ok(_) => None,
+                Err(_) => None, // Lagged: drop the gap, the next in-range event will catch the client up
+            }
+        }
+    });
+
+    Sse::new(stream).keep_alive(axum::response::sse::KeepAlive::default())
+}
+
+/// Stream live single-clip encode progress for `video_id` as Server-Sent
+/// Events, one JSON-encoded `EncodeProgress` per `-progress pipe:1` block
+/// ffmpeg emits - finer-grained than `progress_handler`'s one-event-per-
+/// finished-clip updates, so a frontend can show a live percent/fps/speed/eta
+/// meter for whichever clip is encoding right now.
+pub async fn encode_progress_handler(
+    State(state): State<Arc<AppState>>,
+    AxumPath(video_id): AxumPath<String>,
+) -> Sse<impl futures_util::Stream<Item = Result<axum::response::sse::Event, std::convert::Infallible>>> {
+    use axum::response::sse::Event;
+    use tokio_stream::wrappers::BroadcastStream;
+    use futures_util::StreamExt;
+
+    let rx = state.encode_progress_tx.subscribe();
+    let stream = BroadcastStream::new(rx).filter_map(move |msg| {
+        let video_id = video_id.clone();
+        async move {
+            match msg {
+                Ok(progress) if progress.video_id == video_id => {
+                    let json = serde_json::to_string(&progress).ok()?;
+                    Some(Ok(Event::default().data(json)))
+                }
+                Ok(_) => None,
+                Err(_) => None, // Lagged: drop the gap, the next in-range event will catch the client up
+            }
+        }
+    });
+
+    Sse::new(stream).keep_alive(axum::response::sse::KeepAlive::default())
+}
+
 /// Generate clips from video
 pub async fn clip_handler(
     State(state): State<Arc<AppState>>,
     Json(request): Json<ClipRequest>,
 ) -> Result<Json<ClipResponse>, (StatusCode, Json<ErrorResponse>)> {
+    if let Err(reason) = system_info::check_admission(&state.config) {
+        warn!("[POST /clip] Rejecting request: {}", reason);
+        return Err((
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(ErrorResponse { error: reason }),
+        ));
+    }
+
     let start_time = SystemTime::now();
     let mem_before = system_info::get_memory_usage();
 
-    // Get video metadata - check memory first, then file system (for multi-machine deployments)
+    // Cache key scopes reuse to this exact request shape (max_length/mode/
+    // output_profiles/output_container), not just video_id - otherwise a
+    // second /clip call for the same video with different parameters would
+    // silently get back the first call's clips instead of re-encoding.
+    let request_shape_hash = request.request_shape_hash();
+    let cache_key = format!("{}:{}", request.video_id, request_shape_hash);
+
+    // Reuse clips already generated for this request shape (e.g. a re-upload
+    // that the dedup subsystem aliased to an existing id, or an identical
+    // repeat call) instead of re-clipping
+    if let Some(cached) = state.clip_cache.read().await.get(&cache_key) {
+        info!("[POST /clip] ✅ Reusing {} cached clips for video {}", cached.len(), request.video_id);
+        state.last_served.write().await.insert(request.video_id.clone(), SystemTime::now());
+        return Ok(Json(ClipResponse { clips: cached.clone() }));
+    }
+
+    // Cold cache (e.g. just after a restart): check the persistent store
+    // before falling through to re-generating the clips from scratch
+    match state.db.get_clips(&request.video_id, &request_shape_hash).await {
+        Ok(cached) if !cached.is_empty() => {
+            info!("[POST /clip] ✅ Reusing {} clips for video {} from db", cached.len(), request.video_id);
+            state.clip_cache.write().await.insert(cache_key.clone(), cached.clone());
+            state.last_served.write().await.insert(request.video_id.clone(), SystemTime::now());
+            return Ok(Json(ClipResponse { clips: cached }));
+        }
+        Ok(_) => {}
+        Err(e) => warn!("[POST /clip] ⚠️  Failed to query persisted clips: {}", e),
+    }
+
+    // Get video metadata - check in-memory cache first, then fall back to the
+    // persistent store (handles a cold cache after restart, or a multi-process
+    // deployment where another process handled the upload)
     let video = {
-        // First, try to get from in-memory HashMap
         let videos_read = state.videos.read().await;
         if let Some(video) = videos_read.get(&request.video_id) {
             Some(video.clone())
         } else {
             drop(videos_read); // Release the read lock
-            
-            // Not in memory - check file system (handles multi-machine scenario)
-            info!("[POST /clip] Video not in memory, checking file system for video_id: {}", request.video_id);
-            
-            // Search for files starting with the video_id in uploads directory
-            let mut entries = tokio::fs::read_dir(&state.config.upload_dir).await
-                .map_err(|e| {
-                    error!("[POST /clip] Failed to read uploads directory: {}", e);
-                    (
-                        StatusCode::INTERNAL_SERVER_ERROR,
-                        Json(ErrorResponse {
-                            error: format!("Failed to access uploads directory: {}", e),
-                        }),
-                    )
-                })?;
-            
-            let mut found_file: Option<PathBuf> = None;
-            while let Some(entry) = entries.next_entry().await
-                .map_err(|e| {
-                    error!("[POST /clip] Failed to read directory entry: {}", e);
-                    (
+
+            info!("[POST /clip] Video not in memory, checking metadata store for video_id: {}", request.video_id);
+
+            match state.db.get_video(&request.video_id).await {
+                Ok(Some(video_metadata)) => {
+                    state.videos.write().await.insert(request.video_id.clone(), video_metadata.clone());
+                    info!("[POST /clip] ‚úÖ Loaded video metadata from db and cached in memory");
+                    Some(video_metadata)
+                }
+                Ok(None) => None,
+                Err(e) => {
+                    error!("[POST /clip] Failed to query metadata store: {}", e);
+                    return Err((
                         StatusCode::INTERNAL_SERVER_ERROR,
                         Json(ErrorResponse {
-                            error: format!("Failed to read directory: {}", e),
+                            error: format!("Failed to query metadata store: {}", e),
                         }),
-                    )
-                })? {
-                let path = entry.path();
-                if let Some(file_name) = path.file_name().and_then(|n| n.to_str()) {
-                    if file_name.starts_with(&request.video_id) {
-                        found_file = Some(path);
-                        break;
-                    }
+                    ));
                 }
             }
-            
-            if let Some(file_path) = found_file {
-                info!("[POST /clip] Found video file on disk: {:?}", file_path);
-                
-                // Get file metadata
-                let metadata = tokio::fs::metadata(&file_path).await
-                    .map_err(|e| {
-                        error!("[POST /clip] Failed to get file metadata: {}", e);
-                        (
-                            StatusCode::INTERNAL_SERVER_ERROR,
-                            Json(ErrorResponse {
-                                error: format!("Failed to read file: {}", e),
-                            }),
-                        )
-                    })?;
-                
-                let file_size = metadata.len();
-                
-                // Get video duration
-                let duration = ffmpeg::get_video_duration(&file_path).await
-                    .map_err(|e| {
-                        error!("[POST /clip] Failed to get video duration: {}", e);
-                        (
-                            StatusCode::INTERNAL_SERVER_ERROR,
-                            Json(ErrorResponse {
-                                error: format!("Failed to process video: {}", e),
-                            }),
-                        )
-                    })?;
-                
-                // Extract original filename (remove video_id prefix and dash)
-                let original_name = file_path
-                    .file_name()
-                    .and_then(|n| n.to_str())
-                    .map(|name| {
-                        if let Some(stripped) = name.strip_prefix(&format!("{}-", request.video_id)) {
-                            stripped.to_string()
-                        } else {
-                            name.to_string()
-                        }
-                    })
-                    .unwrap_or_else(|| "video.mp4".to_string());
-                
-                // Create video metadata
-                let video_metadata = VideoMetadata {
-                    id: request.video_id.clone(),
-                    file_path: file_path.clone(),
-                    duration,
-                    original_name: original_name.clone(),
-                    file_size,
-                    uploaded_at: SystemTime::now(),
-                };
-                
-                // Store in memory for future use
-                state.videos.write().await.insert(request.video_id.clone(), video_metadata.clone());
-                info!("[POST /clip] ‚úÖ Loaded video metadata from disk and cached in memory");
-                
-                Some(video_metadata)
-            } else {
-                None
-            }
         }
     }
     .ok_or_else(|| {
@@ -476,13 +748,24 @@ pub async fn clip_handler(
         state.config.limits.default_max_clip_length
     };
     
+    // Mark this video as in-flight so the retention sweep can't evict its
+    // clip set (or the source upload) while generation/delivery is running
+    state.in_progress.write().await.insert(request.video_id.clone());
+
     // Generate clips
-    let clips = generate_time_based_clips(
+    let clips_result = generate_time_based_clips(
         &video.file_path,
         &request.video_id,
         video.duration,
         max_length,
         &state.config,
+        video.width,
+        video.height,
+        request.mode,
+        &request.output_profiles,
+        request.output_container.as_deref(),
+        state.progress_tx.clone(),
+        state.encode_progress_tx.clone(),
     )
     .await
     .map_err(|e| {
@@ -494,7 +777,15 @@ pub async fn clip_handler(
                 error: format!("Failed to generate clips: {}", e),
             }),
         )
-    })?;
+    });
+
+    let clips = match clips_result {
+        Ok(clips) => clips,
+        Err(e) => {
+            state.in_progress.write().await.remove(&request.video_id);
+            return Err(e);
+        }
+    };
 
     let mem_after = system_info::get_memory_usage();
     let total_time = start_time.elapsed().unwrap().as_secs_f64();
@@ -523,8 +814,57 @@ pub async fn clip_handler(
         .join(", ");
     info!("[POST /clip] üìä Clips: {}", clips_str);
 
+    // Cache the generated clips so a duplicate upload or a repeat request with
+    // this exact request shape can be served without re-encoding
+    state
+        .clip_cache
+        .write()
+        .await
+        .insert(cache_key.clone(), clips.clone());
+
+    if let Err(e) = state.db.insert_clips(&request.video_id, &request_shape_hash, &clips).await {
+        warn!("[POST /clip] ⚠️  Failed to persist clips to db: {}", e);
+    }
+
+    // Deliver clips straight to a configured sink (e.g. Telegram) before
+    // housekeeping runs, and only run housekeeping once every upload is
+    // confirmed - a failed delivery shouldn't delete a source the operator
+    // might want to retry with.
+    let publish_confirmed = if state.config.publish.enabled {
+        match &state.config.publish.telegram {
+            Some(telegram) => {
+                let sink = publish::TelegramSink::new(
+                    telegram.bot_token.clone(),
+                    telegram.chat_id.clone(),
+                    telegram.caption_template.clone(),
+                );
+                let output_dir = state.config.output_dir.join(&request.video_id);
+                match publish::publish_clips(sink, &state.config.publish, &request.video_id, &clips, &output_dir).await {
+                    Ok(()) => {
+                        info!("[POST /clip] ✅ Delivered all clips to Telegram");
+                        true
+                    }
+                    Err(e) => {
+                        error!("[POST /clip] ❌ Clip delivery incomplete, keeping source video: {}", e);
+                        false
+                    }
+                }
+            }
+            None => {
+                warn!("[POST /clip] Publishing enabled but no sink is configured, skipping delivery");
+                true
+            }
+        }
+    } else {
+        true
+    };
+
     // Housekeeping: Clean up unneeded files after successful clipping
-    cleanup_after_clipping(&state, &video).await;
+    if publish_confirmed {
+        cleanup_after_clipping(&state, &video).await;
+    }
+
+    state.in_progress.write().await.remove(&request.video_id);
 
     Ok(Json(ClipResponse { clips }))
 }
@@ -536,28 +876,75 @@ async fn generate_time_based_clips(
     duration: f64,
     max_length: f64,
     config: &Config,
+    width: Option<u32>,
+    height: Option<u32>,
+    mode: crate::models::ClipMode,
+    requested_profiles: &[String],
+    output_container: Option<&str>,
+    progress_tx: tokio::sync::broadcast::Sender<crate::models::ClipProgress>,
+    encode_progress_tx: tokio::sync::broadcast::Sender<crate::models::EncodeProgress>,
 ) -> anyhow::Result<Vec<Clip>> {
     use std::sync::Arc;
-    use tokio::sync::Semaphore;
+    use crate::models::ClipMode;
 
     let output_base = config.output_dir.join(video_id);
     tokio::fs::create_dir_all(&output_base).await?;
 
     let mem_start = system_info::get_memory_usage();
 
+    // Scene-aware splitting: detect cut points up front, either to snap the
+    // fixed-length boundaries to the nearest one, or (in SceneDetect mode) to
+    // drive clip placement outright.
+    let scene_cuts = if config.scene_detection.enabled || mode == ClipMode::SceneDetect {
+        match scene::detect_scene_cuts(input_path, &config.scene_detection).await {
+            Ok(cuts) => {
+                info!("[generateClips] 🎬 Detected {} scene cuts for scene-aware splitting", cuts.len());
+                cuts
+            }
+            Err(e) => {
+                warn!("[generateClips] Scene detection failed, falling back to fixed-length clips: {}", e);
+                Vec::new()
+            }
+        }
+    } else {
+        Vec::new()
+    };
+
     // Calculate all clip segments
     let mut segments = Vec::new();
-    let mut start = 0.0;
-    let mut clip_index = 1;
 
-    while start < duration {
-        let clip_duration = (max_length).min(duration - start);
+    if mode == ClipMode::SceneDetect && !scene_cuts.is_empty() {
+        let raw_segments = scene::build_scene_aware_segments(
+            &scene_cuts,
+            duration,
+            max_length,
+            config.optimization.min_clip_duration,
+        );
+        for (i, (start, clip_duration)) in raw_segments.into_iter().enumerate() {
+            segments.push((start, clip_duration, i + 1));
+        }
+    } else {
+        let mut start = 0.0;
+        let mut clip_index = 1;
+
+        while start < duration {
+            let target_end = (start + max_length).min(duration);
+            let end = if !scene_cuts.is_empty() && target_end < duration {
+                // Don't snap past the remaining duration, and keep the snap window
+                // tight relative to min_scene_len so we don't chase a distant cut.
+                let snap_window = config.scene_detection.min_scene_len.max(1.0) * 2.0;
+                scene::snap_to_nearest_cut(target_end, &scene_cuts, snap_window).min(duration)
+            } else {
+                target_end
+            };
+            let clip_duration = (end - start).max(0.1);
 
-        if clip_duration >= config.optimization.min_clip_duration || start + clip_duration >= duration {
-            segments.push((start, clip_duration, clip_index));
-            clip_index += 1;
+            if clip_duration >= config.optimization.min_clip_duration || start + clip_duration >= duration {
+                segments.push((start, clip_duration, clip_index));
+                clip_index += 1;
+            }
+            start += clip_duration;
         }
-        start += clip_duration;
     }
 
     let total_clips = segments.len();
@@ -574,111 +961,173 @@ async fn generate_time_based_clips(
         sys_info.memory_free_gb, sys_info.cpus
     );
 
-    // Process clips in parallel with semaphore for concurrency control
-    // Use adaptive concurrency based on system resources
-    let semaphore = Arc::new(Semaphore::new(config.max_concurrent_clips));
-    let mut handles = Vec::new();
-
-    // Pre-allocate handles vector for better performance
-    handles.reserve(segments.len());
-
-    for (clip_start, clip_duration, index) in segments {
-        let permit = semaphore.clone().acquire_owned().await?;
-        let input_path = input_path.clone();
-        let output_base = output_base.clone();
-        let video_id = video_id.to_string();
-
-        let ffmpeg_config = config.ffmpeg.clone();
-        let concurrent_clips = config.max_concurrent_clips;
-        let handle = tokio::spawn(async move {
-            let _permit = permit; // Hold permit until clip is done
-            let clip_id = format!("clip-{}", index);
-            let output_path = output_base.join(format!("{}.mp4", clip_id));
-
-            let clip_start_time = SystemTime::now();
-            let clip_mem_before = system_info::get_memory_usage();
-            let clip_free_mem = system_info::get_system_info().memory_free_gb;
+    // Resolve the requested output_profiles against the config-defined catalog.
+    // `None` means "just the single default rendition from config.ffmpeg",
+    // which is also the fallback if every requested name is unknown.
+    let profiles: Vec<Option<crate::config::OutputProfile>> = if requested_profiles.is_empty() {
+        vec![None]
+    } else {
+        let mut resolved: Vec<Option<crate::config::OutputProfile>> = requested_profiles
+            .iter()
+            .filter_map(|name| {
+                let found = config.output_profiles.iter().find(|p| &p.name == name);
+                if found.is_none() {
+                    warn!("[generateClips] Unknown output profile '{}', skipping", name);
+                }
+                found.cloned().map(Some)
+            })
+            .collect();
+        if resolved.is_empty() {
+            resolved.push(None);
+        }
+        resolved
+    };
 
-            info!(
-                "[generateClips] üé¨ Clip {}/{} ({:.1}s-{:.1}s)",
-                index,
-                total_clips,
-                clip_start,
-                clip_start + clip_duration
-            );
-            info!(
-                "[generateClips] üíæ Memory before clip: RSS={:.2}MB, Free={:.2}GB",
-                clip_mem_before.rss_mb, clip_free_mem
+    // Expand (segment × profile) into independent encoding jobs sharing the
+    // same worker pool, so a multi-rendition request doesn't get more
+    // concurrency than a single-rendition one.
+    let mut jobs = Vec::with_capacity(segments.len() * profiles.len());
+    for (clip_start, clip_duration, index) in &segments {
+        for profile in &profiles {
+            jobs.push((*clip_start, *clip_duration, *index, profile.clone()));
+        }
+    }
+
+    // Process clips through a bounded worker pool: jobs are pushed onto a
+    // channel and a fixed number of worker tasks (sized from
+    // `config.max_concurrent_clips`, itself derived from
+    // `std::thread::available_parallelism` unless overridden) pull from it,
+    // so ffmpeg concurrency is capped by construction instead of by a
+    // semaphore racing arbitrarily-many spawned tasks.
+    let pool_size = config.max_concurrent_clips.max(1);
+    let strip_metadata = config.privacy.strip_metadata;
+
+    // Perceptual-hash dedup of produced clips: long uploads often repeat a
+    // segment (intro, replay), which would otherwise surface as near-identical
+    // Clips. Shared across concurrently-running jobs since a duplicate can be
+    // detected against a clip still being encoded by another worker. Scoped
+    // per output-profile (see `ClipJobContext::clip_dedup_trees`) so a
+    // multi-profile request doesn't dedup one profile's renditions against
+    // another's.
+    let clip_dedup_trees = Arc::new(tokio::sync::Mutex::new(std::collections::HashMap::new()));
+
+    // A request-level `output_container` wins over the configured default,
+    // but only for a container we actually know how to serve - anything else
+    // (typo, future value) silently falls back rather than failing the run.
+    let mut output_format = config.output_format.clone();
+    match output_container {
+        Some(container @ ("mp4" | "hls")) => {
+            output_format.container = container.to_string();
+        }
+        Some(other) => {
+            warn!(
+                "[generateClips] Unknown output_container '{}', keeping configured '{}'",
+                other, output_format.container
             );
-            match ffmpeg::generate_clip(&input_path, &output_path, clip_start, clip_duration, &ffmpeg_config, concurrent_clips).await
-            {
-                Ok(()) => {
-                    let clip_time = clip_start_time.elapsed().unwrap().as_secs_f64();
-                    let clip_mem_after = system_info::get_memory_usage();
-                    let clip_mem_delta = clip_mem_after.rss_mb - clip_mem_before.rss_mb;
-                    let clip_free_mem = system_info::get_system_info().memory_free_gb;
+        }
+        None => {}
+    }
 
-                    info!("[generateClips] ‚úì Clip {} done in {:.2}s", index, clip_time);
-                    info!(
-                        "[generateClips] üíæ Memory after clip: RSS={:.2}MB ({}{:.2}MB), Free={:.2}GB",
-                        clip_mem_after.rss_mb,
-                        if clip_mem_delta > 0.0 { "+" } else { "" },
-                        clip_mem_delta,
-                        clip_free_mem
-                    );
+    let total_jobs = jobs.len();
+    let ctx = Arc::new(ClipJobContext {
+        input_path: input_path.clone(),
+        output_base: output_base.clone(),
+        video_id: video_id.to_string(),
+        ffmpeg_config: config.ffmpeg.clone(),
+        concurrent_clips: config.max_concurrent_clips,
+        output_format,
+        thread_policy: config.thread_policy.clone(),
+        resolution: width.zip(height),
+        width,
+        height,
+        dedup_enabled: config.dedup.enabled,
+        clip_dedup_tolerance: config.dedup.clip_dedup_tolerance_bits,
+        clip_dedup_trees,
+        strip_metadata,
+        total_clips,
+        min_free_memory_gb: config.admission.min_free_memory_gb,
+        scene_snap: config.scene_snap.clone(),
+        encode_progress_tx,
+    });
 
-                    // Generate thumbnail for the clip (extract frame at 0.2s or 2% of duration)
-                    let thumbnail_path = output_base.join(format!("{}.jpg", clip_id));
-                    let thumbnail_time = 0.2f64.min(clip_duration * 0.02); // Use 0.2s or 2% of clip duration, whichever is smaller
-                    
-                    match ffmpeg::generate_thumbnail(&output_path, &thumbnail_path, thumbnail_time).await {
-                        Ok(()) => {
-                            info!("[generateClips] ‚úì Thumbnail {} generated at {:.2}s", clip_id, thumbnail_time);
-                        }
-                        Err(e) => {
-                            warn!("[generateClips] ‚ö†Ô∏è  Failed to generate thumbnail for {}: {}", clip_id, e);
-                            // Continue even if thumbnail generation fails - clip is still valid
-                        }
-                    }
+    let (job_tx, job_rx) = tokio::sync::mpsc::channel::<ClipJob>(total_jobs.max(1));
+    for (clip_start, clip_duration, index, profile) in jobs {
+        let _ = job_tx
+            .send(ClipJob {
+                clip_start,
+                clip_duration,
+                index,
+                profile,
+            })
+            .await;
+    }
+    drop(job_tx); // Closes the channel so workers exit once it's drained
+    let job_rx = Arc::new(tokio::sync::Mutex::new(job_rx));
 
-                    Ok(Clip {
-                        id: clip_id.clone(),
-                        url: format!("/clips/{}/{}.mp4", video_id, clip_id),
-                        thumbnail_url: format!("/clips/{}/{}.jpg", video_id, clip_id),
-                        duration: clip_duration,
-                    })
-                }
-                Err(e) => {
-                    let clip_time = clip_start_time.elapsed().unwrap().as_secs_f64();
-                    error!(
-                        "[generateClips] ‚úó Clip {} failed after {:.2}s: {}",
-                        index, clip_time, e
+    let (result_tx, mut result_rx) =
+        tokio::sync::mpsc::unbounded_channel::<(usize, anyhow::Result<Option<Clip>>, f64)>();
+
+    for _ in 0..pool_size {
+        let job_rx = job_rx.clone();
+        let ctx = ctx.clone();
+        let result_tx = result_tx.clone();
+        tokio::spawn(async move {
+            loop {
+                let job = {
+                    let mut rx = job_rx.lock().await;
+                    rx.recv().await
+                };
+                let Some(job) = job else { break };
+                let index = job.index;
+
+                // Memory-aware backpressure: if free memory has dropped below
+                // the admission-control threshold, pause this worker briefly
+                // before picking up another job rather than piling on more
+                // concurrent ffmpeg processes.
+                let free_gb = system_info::get_system_info().memory_free_gb;
+                if free_gb < ctx.min_free_memory_gb {
+                    warn!(
+                        "[generateClips] ⚠️  Free memory {:.2}GB below {:.2}GB threshold, pausing worker",
+                        free_gb, ctx.min_free_memory_gb
                     );
-                    Err(e)
+                    tokio::time::sleep(std::time::Duration::from_secs(2)).await;
                 }
+
+                let job_start = SystemTime::now();
+                let result = run_clip_job(job, &ctx).await;
+                let elapsed = job_start.elapsed().unwrap().as_secs_f64();
+                let _ = result_tx.send((index, result, elapsed));
             }
         });
-
-        handles.push((index, handle));
     }
+    drop(result_tx);
 
-    // Collect results - use try_join_all for better error handling
-    // Pre-allocate clips vector
-    let mut clips = Vec::with_capacity(handles.len());
-    
-    // Process handles in order but allow failures to not block others
-    for (index, handle) in handles {
-        match handle.await {
-            Ok(Ok(clip)) => clips.push(clip),
-            Ok(Err(e)) => {
-                error!("[generateClips] Failed to generate clip {}: {}", index, e);
-                // Continue with other clips
+    // Collect results as they arrive, publishing live progress on the
+    // broadcast channel so an SSE client (GET /progress/{video_id}) can
+    // follow the run instead of only seeing it in logs at the end.
+    let mut clips = Vec::with_capacity(total_jobs);
+    let mut clips_done = 0usize;
+    while let Some((index, result, elapsed)) = result_rx.recv().await {
+        clips_done += 1;
+        match result {
+            Ok(Some(clip)) => clips.push(clip),
+            Ok(None) => {
+                // Discarded as a perceptual-hash duplicate of another clip in this batch
             }
             Err(e) => {
-                error!("[generateClips] Task for clip {} panicked: {}", index, e);
+                error!("[generateClips] Failed to generate clip {}: {}", index, e);
                 // Continue with other clips
             }
         }
+
+        let _ = progress_tx.send(crate::models::ClipProgress {
+            video_id: video_id.to_string(),
+            stage: "encoding".to_string(),
+            clips_done,
+            clips_total: total_clips,
+            last_clip_index: Some(index),
+            last_clip_elapsed_secs: Some(elapsed),
+        });
     }
 
     // Sort clips by index
@@ -708,9 +1157,314 @@ async fn generate_time_based_clips(
         clips.len()
     );
 
+    let _ = progress_tx.send(crate::models::ClipProgress {
+        video_id: video_id.to_string(),
+        stage: "done".to_string(),
+        clips_done: clips.len(),
+        clips_total: total_clips,
+        last_clip_index: None,
+        last_clip_elapsed_secs: None,
+    });
+
     Ok(clips)
 }
 
+/// Immutable context shared by every worker in the clip-generation pool, so
+/// the per-job closure only needs to carry what actually varies per job
+/// (`ClipJob`).
+struct ClipJobContext {
+    input_path: PathBuf,
+    output_base: PathBuf,
+    video_id: String,
+    ffmpeg_config: crate::config::FfmpegConfig,
+    concurrent_clips: usize,
+    output_format: crate::config::OutputFormatConfig,
+    thread_policy: String,
+    resolution: Option<(u32, u32)>,
+    width: Option<u32>,
+    height: Option<u32>,
+    dedup_enabled: bool,
+    clip_dedup_tolerance: u32,
+    // Keyed by output-profile name (`None` for the single default rendition)
+    // so a multi-profile request (e.g. `["hevc_1080p", "av1_720p"]`) never
+    // compares one profile's renditions against another's - they encode the
+    // same timeline segments at different resolutions/codecs and would
+    // otherwise perceptual-hash to the same value, causing every profile but
+    // the first to finish to be discarded as a "near-duplicate".
+    clip_dedup_trees: Arc<tokio::sync::Mutex<std::collections::HashMap<Option<String>, dedup::BkTree>>>,
+    strip_metadata: bool,
+    total_clips: usize,
+    min_free_memory_gb: f64,
+    scene_snap: crate::config::SceneSnapConfig,
+    encode_progress_tx: tokio::sync::broadcast::Sender<crate::models::EncodeProgress>,
+}
+
+/// One (segment × output-profile) encoding job for the worker pool in
+/// `generate_time_based_clips`.
+struct ClipJob {
+    clip_start: f64,
+    clip_duration: f64,
+    index: usize,
+    profile: Option<crate::config::OutputProfile>,
+}
+
+/// Encode a single clip job: render it with ffmpeg, dedup/strip-metadata/
+/// thumbnail it, and build the `Clip` the caller returns to the client.
+/// Returns `Ok(None)` when the clip was discarded as a near-duplicate.
+async fn run_clip_job(job: ClipJob, ctx: &ClipJobContext) -> anyhow::Result<Option<Clip>> {
+    let ClipJob { clip_start, clip_duration, index, profile } = job;
+
+    // Scene-snapping nudges this job's bounds to real shot boundaries so the
+    // clip doesn't start or end mid-shot. This is a separate, opt-in knob from
+    // the batch scene-aware segment planning above (`scene::detect_scene_cuts`) -
+    // it reuses ffmpeg's own `scene` filter rather than re-running that analysis.
+    let (clip_start, clip_duration) = if ctx.scene_snap.enabled {
+        match ffmpeg::snap_clip_bounds(&ctx.input_path, clip_start, clip_duration, &ctx.scene_snap).await {
+            Ok(snapped) => snapped,
+            Err(e) => {
+                warn!(
+                    "[generateClips] Scene-snap failed for clip {}, using requested bounds: {}",
+                    index, e
+                );
+                (clip_start, clip_duration)
+            }
+        }
+    } else {
+        (clip_start, clip_duration)
+    };
+
+    let clip_id = match &profile {
+        Some(p) => format!("clip-{}-{}", index, p.name),
+        None => format!("clip-{}", index),
+    };
+    let output_path = ctx.output_base.join(format!("{}.mp4", clip_id));
+    let max_height = profile.as_ref().and_then(|p| p.max_height);
+
+    let clip_start_time = SystemTime::now();
+    let clip_mem_before = system_info::get_memory_usage();
+    let clip_free_mem = system_info::get_system_info().memory_free_gb;
+
+    info!(
+        "[generateClips] üé¨ Clip {}/{} ({:.1}s-{:.1}s)",
+        index,
+        ctx.total_clips,
+        clip_start,
+        clip_start + clip_duration
+    );
+    info!(
+        "[generateClips] üíæ Memory before clip: RSS={:.2}MB, Free={:.2}GB",
+        clip_mem_before.rss_mb, clip_free_mem
+    );
+
+    let mut ffmpeg_config = ctx.ffmpeg_config.clone();
+    if let Some(p) = &profile {
+        if let Some(crf) = p.crf {
+            ffmpeg_config.crf = crf;
+        }
+        let mut advanced = ffmpeg_config
+            .advanced
+            .clone()
+            .unwrap_or_else(crate::config::default_ffmpeg_advanced_config);
+        advanced.default_video_codec = p.codec.clone();
+        ffmpeg_config.advanced = Some(advanced);
+    }
+    if let Some(target) = ffmpeg_config.target_quality.clone().filter(|t| t.enabled) {
+        match vmaf::solve_crf(&ctx.input_path, clip_start, &target, &ffmpeg_config.preset).await {
+            Ok(crf) => {
+                info!(
+                    "[generateClips] Clip {}: target VMAF {:.1} solved to crf={}",
+                    index, target.target_vmaf, crf
+                );
+                ffmpeg_config.crf = crf;
+            }
+            Err(e) => {
+                warn!(
+                    "[generateClips] Failed to solve target-quality CRF for clip {}, falling back to configured crf={}: {}",
+                    index, ffmpeg_config.crf, e
+                );
+            }
+        }
+    }
+    if let Some(adaptive) = ffmpeg_config.adaptive_quality.clone().filter(|a| a.enabled) {
+        match vmaf::solve_crf_adaptive(&ctx.input_path, clip_start, &adaptive, &ffmpeg_config.preset).await {
+            Ok(crf) => {
+                info!(
+                    "[generateClips] Clip {}: adaptive VMAF {:.1} solved to crf={}",
+                    index, adaptive.vmaf, crf
+                );
+                ffmpeg_config.crf = crf;
+            }
+            Err(e) => {
+                warn!(
+                    "[generateClips] Failed to solve adaptive-quality CRF for clip {}, falling back to configured crf={}: {}",
+                    index, ffmpeg_config.crf, e
+                );
+            }
+        }
+    }
+
+    let progress_reporter = crate::models::EncodeProgressReporter {
+        tx: ctx.encode_progress_tx.clone(),
+        video_id: ctx.video_id.clone(),
+        clip_id: clip_id.clone(),
+        total_duration: clip_duration,
+    };
+
+    let result = match ffmpeg_config.retry.clone().filter(|r| r.enabled) {
+        Some(retry) => {
+            broker::generate_clip_with_retry(
+                &ctx.input_path,
+                &output_path,
+                clip_start,
+                clip_duration,
+                &ffmpeg_config,
+                ctx.concurrent_clips,
+                &ctx.output_format,
+                &ctx.thread_policy,
+                ctx.resolution,
+                max_height,
+                &retry,
+                Some(&progress_reporter),
+            )
+            .await
+        }
+        None => {
+            ffmpeg::generate_clip(
+                &ctx.input_path,
+                &output_path,
+                clip_start,
+                clip_duration,
+                &ffmpeg_config,
+                ctx.concurrent_clips,
+                &ctx.output_format,
+                &ctx.thread_policy,
+                ctx.resolution,
+                max_height,
+                Some(&progress_reporter),
+            )
+            .await
+        }
+    };
+
+    match result {
+        Ok(()) => {
+            let clip_time = clip_start_time.elapsed().unwrap().as_secs_f64();
+            let clip_mem_after = system_info::get_memory_usage();
+            let clip_mem_delta = clip_mem_after.rss_mb - clip_mem_before.rss_mb;
+            let clip_free_mem = system_info::get_system_info().memory_free_gb;
+
+            info!("[generateClips] ‚úì Clip {} done in {:.2}s", index, clip_time);
+            info!(
+                "[generateClips] üíæ Memory after clip: RSS={:.2}MB ({}{:.2}MB), Free={:.2}GB",
+                clip_mem_after.rss_mb,
+                if clip_mem_delta > 0.0 { "+" } else { "" },
+                clip_mem_delta,
+                clip_free_mem
+            );
+
+            // Perceptual-hash dedup: skip writing the thumbnail and
+            // returning this clip if it's a near-duplicate (e.g. a
+            // repeated intro or replay) of one already produced in
+            // this batch.
+            if ctx.dedup_enabled {
+                let profile_key = profile.as_ref().map(|p| p.name.clone());
+                match dedup::compute_clip_hash(&output_path, clip_duration).await {
+                    Ok(hash) => {
+                        let mut trees = ctx.clip_dedup_trees.lock().await;
+                        let tree = trees.entry(profile_key).or_insert_with(dedup::BkTree::new);
+                        if let Some(existing) = tree.find_within(&hash, ctx.clip_dedup_tolerance) {
+                            info!(
+                                "[generateClips] Clip {} is a near-duplicate of {}, discarding",
+                                clip_id, existing
+                            );
+                            drop(trees);
+                            let _ = tokio::fs::remove_file(&output_path).await;
+                            return Ok(None);
+                        }
+                        tree.insert(clip_id.clone(), hash);
+                    }
+                    Err(e) => {
+                        warn!("[generateClips] Failed to compute dedup hash for {}: {}", clip_id, e);
+                    }
+                }
+            }
+            // Strip GPS/device/timestamp metadata carried through from the source
+            // before the clip is ever served
+            if ctx.strip_metadata {
+                if let Err(e) = ffmpeg::strip_metadata(&output_path).await {
+                    warn!("[generateClips] Failed to strip metadata from {}: {}", clip_id, e);
+                    // Continue serving the clip even if stripping failed
+                }
+            }
+
+            // Generate thumbnail for the clip, picking the sharpest/most
+            // representative of several candidate frames rather than a
+            // single fixed timestamp that might land on a black/blurry frame
+            let thumbnail_path = ctx.output_base.join(format!("{}.jpg", clip_id));
+            let fallback_thumbnail_time = 0.2f64.min(clip_duration * 0.02); // Use 0.2s or 2% of clip duration, whichever is smaller
+            let thumbnail_time = ffmpeg::select_best_thumbnail_frame(&output_path, clip_duration, fallback_thumbnail_time).await;
+
+            match ffmpeg::generate_thumbnail(&output_path, &thumbnail_path, thumbnail_time).await {
+                Ok(()) => {
+                    info!("[generateClips] ‚úì Thumbnail {} generated at {:.2}s", clip_id, thumbnail_time);
+                }
+                Err(e) => {
+                    warn!("[generateClips] ‚ö†Ô∏è  Failed to generate thumbnail for {}: {}", clip_id, e);
+                    // Continue even if thumbnail generation fails - clip is still valid
+                }
+            }
+
+            // HLS mode: segment the fragmented-mp4 clip into a playlist and
+            // point clients at the .m3u8 instead of the standalone mp4
+            let url = if ctx.output_format.container == "hls" {
+                match ffmpeg::mux_hls(&output_path, &clip_id, &ctx.output_format).await {
+                    Ok(()) => format!(
+                        "/clips/{}/{}",
+                        ctx.video_id,
+                        ffmpeg::playlist_file_name(&clip_id, &ctx.output_format.playlist_name)
+                    ),
+                    Err(e) => {
+                        warn!("[generateClips] Failed to mux HLS for {}: {}", clip_id, e);
+                        format!("/clips/{}/{}.mp4", ctx.video_id, clip_id)
+                    }
+                }
+            } else {
+                format!("/clips/{}/{}.mp4", ctx.video_id, clip_id)
+            };
+
+            // Report the dimensions this rendition actually encoded at - scaled
+            // down to max_height (preserving aspect ratio) rather than the
+            // source's, when this profile downscaled the clip.
+            let (clip_width, clip_height) = match (max_height, ctx.width, ctx.height) {
+                (Some(max_h), Some(w), Some(h)) if h > max_h => {
+                    let scaled_w = ((w as f64 * max_h as f64 / h as f64 / 2.0).round() as u32) * 2;
+                    (Some(scaled_w), Some(max_h))
+                }
+                _ => (ctx.width, ctx.height),
+            };
+
+            Ok(Some(Clip {
+                id: clip_id.clone(),
+                url,
+                thumbnail_url: format!("/clips/{}/{}.jpg", ctx.video_id, clip_id),
+                duration: clip_duration,
+                width: clip_width,
+                height: clip_height,
+                profile: profile.as_ref().map(|p| p.name.clone()),
+                codec: profile.as_ref().map(|p| p.codec.clone()),
+            }))
+        }
+        Err(e) => {
+            let clip_time = clip_start_time.elapsed().unwrap().as_secs_f64();
+            error!(
+                "[generateClips] ‚úó Clip {} failed after {:.2}s: {}",
+                index, clip_time, e
+            );
+            Err(e)
+        }
+    }
+}
+
 /// Clean up unneeded files after successful clipping
 async fn cleanup_after_clipping(state: &Arc<AppState>, video: &VideoMetadata) {
     info!("[cleanup] üßπ Starting housekeeping for video: {}", video.id);
@@ -748,7 +1502,143 @@ async fn cleanup_after_clipping(state: &Arc<AppState>, video: &VideoMetadata) {
     } else {
         info!("[cleanup] ‚ÑπÔ∏è  Video metadata not found in state: {}", video.id);
     }
+    drop(videos);
+
+    // Keep the metadata store in sync with the in-memory cache so a later
+    // cold-start lookup doesn't resurrect a video whose file is already gone
+    if let Err(e) = state.db.delete_video(&video.id).await {
+        error!("[cleanup] ‚ùå Failed to remove video from metadata store {}: {}", video.id, e);
+    }
 
     info!("[cleanup] ‚úÖ Housekeeping complete for video: {}", video.id);
 }
 
+/// Serve a generated clip from `config.output_dir`, honoring `Range:
+/// bytes=a-b` requests so browser scrubbing and mobile players can seek
+/// without re-downloading from the start. Combined with the `faststart`
+/// ffmpeg flag (moov atom ahead of mdat), this lets playback begin before
+/// the whole file has arrived. Also serves an `hls`-mode clip's `.m3u8`
+/// playlist and `.m4s` segments with the content types HLS players expect -
+/// this handler is registered on the same `/clips/*path` route regardless of
+/// which container a clip was produced in.
+pub async fn serve_clip_handler(
+    State(state): State<Arc<AppState>>,
+    AxumPath(path): AxumPath<String>,
+    headers: HeaderMap,
+) -> Response {
+    let file_path = state.config.output_dir.join(&path);
+
+    // Reject traversal outside the output directory (e.g. "../../etc/passwd").
+    // `Path::starts_with` only compares components and does not resolve `..`,
+    // so a path like "video1/../../../etc/passwd" would pass it even though it
+    // escapes output_dir - canonicalize both sides and compare the resolved,
+    // symlink-free paths instead.
+    let canonical_output_dir = match tokio::fs::canonicalize(&state.config.output_dir).await {
+        Ok(p) => p,
+        Err(_) => return (StatusCode::NOT_FOUND, "clip not found").into_response(),
+    };
+    let canonical_file_path = match tokio::fs::canonicalize(&file_path).await {
+        Ok(p) => p,
+        Err(_) => return (StatusCode::NOT_FOUND, "clip not found").into_response(),
+    };
+    if !canonical_file_path.starts_with(&canonical_output_dir) {
+        return (StatusCode::FORBIDDEN, "invalid path").into_response();
+    }
+
+    let metadata = match tokio::fs::metadata(&canonical_file_path).await {
+        Ok(m) if m.is_file() => m,
+        _ => return (StatusCode::NOT_FOUND, "clip not found").into_response(),
+    };
+    let file_size = metadata.len();
+
+    // Clip paths are `{video_id}/{clip_id}.ext` under output_dir - record that
+    // this video was just served for the `least_recently_served` retention policy
+    if let Some(video_id) = path.split('/').next() {
+        state.last_served.write().await.insert(video_id.to_string(), SystemTime::now());
+    }
+
+    let content_type = match canonical_file_path.extension().and_then(|e| e.to_str()) {
+        Some("mp4") => "video/mp4",
+        Some("m3u8") => "application/vnd.apple.mpegurl",
+        Some("m4s") | Some("mp4f") => "video/iso.segment",
+        Some("jpg") | Some("jpeg") => "image/jpeg",
+        _ => "application/octet-stream",
+    };
+
+    let range = headers
+        .get(header::RANGE)
+        .and_then(|v| v.to_str().ok())
+        .and_then(parse_range_header);
+
+    let (start, end, status) = match range {
+        Some((start, end)) => {
+            let end = end.unwrap_or(file_size.saturating_sub(1)).min(file_size.saturating_sub(1));
+            (start, end, StatusCode::PARTIAL_CONTENT)
+        }
+        None => (0, file_size.saturating_sub(1), StatusCode::OK),
+    };
+
+    if file_size == 0 || start > end || start >= file_size {
+        return Response::builder()
+            .status(StatusCode::RANGE_NOT_SATISFIABLE)
+            .header(header::CONTENT_RANGE, format!("bytes */{}", file_size))
+            .body(Body::empty())
+            .unwrap();
+    }
+
+    let mut file = match tokio::fs::File::open(&canonical_file_path).await {
+        Ok(f) => f,
+        Err(_) => return (StatusCode::NOT_FOUND, "clip not found").into_response(),
+    };
+
+    if file.seek(std::io::SeekFrom::Start(start)).await.is_err() {
+        return (StatusCode::INTERNAL_SERVER_ERROR, "failed to seek clip").into_response();
+    }
+
+    let chunk_len = end - start + 1;
+    let mut buf = Vec::with_capacity(chunk_len as usize);
+    if let Err(e) = file.take(chunk_len).read_to_end(&mut buf).await {
+        error!("[GET /clips] ‚ùå Failed to read clip slice {}: {}", path, e);
+        return (StatusCode::INTERNAL_SERVER_ERROR, "failed to read clip").into_response();
+    }
+
+    let mut response = Response::builder()
+        .status(status)
+        .header(header::CONTENT_TYPE, content_type)
+        .header(header::CONTENT_LENGTH, chunk_len.to_string())
+        .header(header::ACCEPT_RANGES, "bytes");
+
+    if status == StatusCode::PARTIAL_CONTENT {
+        response = response.header(
+            header::CONTENT_RANGE,
+            format!("bytes {}-{}/{}", start, end, file_size),
+        );
+    }
+
+    response.body(Body::from(buf)).unwrap()
+}
+
+/// Parse a `Range: bytes=start-end` header value into an (start, optional end)
+/// pair. Only the single-range `bytes=` form is supported, matching what
+/// browser video players and curl's `--range` actually send.
+fn parse_range_header(value: &str) -> Option<(u64, Option<u64>)> {
+    let spec = value.strip_prefix("bytes=")?;
+    let (start_str, end_str) = spec.split_once('-')?;
+
+    if start_str.is_empty() {
+        // Suffix range "bytes=-500" means "last 500 bytes" - not needed by
+        // any client this backend serves today, so leave it unsupported
+        // rather than guess at total length here.
+        return None;
+    }
+
+    let start = start_str.parse::<u64>().ok()?;
+    let end = if end_str.is_empty() {
+        None
+    } else {
+        Some(end_str.parse::<u64>().ok()?)
+    };
+
+    Some((start, end))
+}
+
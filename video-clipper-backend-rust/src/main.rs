@@ -1,6 +1,6 @@
 use axum::{
     extract::DefaultBodyLimit,
-    routing::post,
+    routing::{get, post},
     Router,
 };
 use std::{
@@ -10,19 +10,27 @@ use std::{
 use tokio::sync::RwLock;
 use tower_http::{
     cors::CorsLayer,
-    services::ServeDir,
     trace::TraceLayer,
 };
 use tracing::info;
 
+mod broker;
+mod cleanup;
+mod cli;
 mod config;
+mod db;
+mod dedup;
+mod diagnostics;
 mod ffmpeg;
 mod handlers;
 mod models;
+mod publish;
+mod scene;
 mod system_info;
+mod vmaf;
 
 use config::Config;
-use handlers::{clip_handler, upload_handler};
+use handlers::{clip_handler, debug_state_handler, duplicates_handler, encode_progress_handler, progress_handler, serve_clip_handler, upload_handler};
 use models::AppState;
 
 #[tokio::main]
@@ -32,12 +40,25 @@ async fn main() -> anyhow::Result<()> {
         .with_env_filter("video_clipper_backend=debug,tower_http=debug")
         .init();
 
-    // Load configuration (from config.toml or env vars)
-    let config = Config::load().unwrap_or_else(|e| {
-        eprintln!("Warning: Failed to load config: {}. Using defaults.", e);
-        Config::default()
-    });
-    
+    let cli = cli::CliArgs::parse_args();
+
+    // Load configuration (config.toml / env vars), then let CLI flags override
+    // on top: CLI > env > config.toml > defaults
+    let config = Config::load()
+        .unwrap_or_else(|e| {
+            eprintln!("Warning: Failed to load config: {}. Using defaults.", e);
+            Config::default()
+        })
+        .apply_cli_overrides(&cli);
+
+    if cli.print_config {
+        match toml::to_string_pretty(&config.to_config_file()) {
+            Ok(toml_str) => println!("{}", toml_str),
+            Err(e) => eprintln!("Failed to serialize resolved config: {}", e),
+        }
+        return Ok(());
+    }
+
     // Ensure directories exist
     tokio::fs::create_dir_all(&config.upload_dir).await?;
     tokio::fs::create_dir_all(&config.output_dir).await?;
@@ -45,12 +66,43 @@ async fn main() -> anyhow::Result<()> {
     // Print system info at startup
     system_info::print_startup_info(&config);
 
+    // Open the persistent metadata store (SQLite), running any pending
+    // schema migrations, before accepting traffic
+    let db = db::Db::connect(&config.db_path).await?;
+    info!("📦 Metadata store ready at {:?}", config.db_path);
+
+    // Rehydrate the in-memory video cache and perceptual-hash dedup index
+    // from the metadata store, so a restart doesn't silently forget every
+    // video uploaded before it (duplicate-upload detection and the clip
+    // cache it feeds would otherwise go cold until each video is re-uploaded).
+    let persisted_videos = db.get_all_videos().await?;
+    let mut dedup_tree = dedup::BkTree::new();
+    let mut videos = HashMap::new();
+    for video in persisted_videos {
+        if let Some(fingerprint) = video.fingerprint.clone() {
+            dedup_tree.insert(video.id.clone(), fingerprint);
+        }
+        videos.insert(video.id.clone(), video);
+    }
+    info!("📦 Rehydrated {} video(s) from metadata store", videos.len());
+
     // Create app state
     let app_state = Arc::new(AppState {
-        videos: Arc::new(RwLock::new(HashMap::new())),
+        videos: Arc::new(RwLock::new(videos)),
         config: config.clone(),
+        dedup_tree: Arc::new(RwLock::new(dedup_tree)),
+        clip_cache: Arc::new(RwLock::new(HashMap::new())),
+        db,
+        in_progress: Arc::new(RwLock::new(std::collections::HashSet::new())),
+        last_served: Arc::new(RwLock::new(HashMap::new())),
+        progress_tx: tokio::sync::broadcast::channel(64).0,
+        encode_progress_tx: tokio::sync::broadcast::channel(256).0,
+        streaming_ingest_inflight: Arc::new(std::sync::atomic::AtomicUsize::new(0)),
     });
 
+    // Start periodic cleanup of old clips/uploads, disk-budget aware
+    cleanup::start_cleanup_task(app_state.clone());
+
     // Build router
     // Set body size limit to 500MB (matching max_file_size config)
     // This is required for Axum's Multipart extractor to handle large files
@@ -65,7 +117,11 @@ async fn main() -> anyhow::Result<()> {
     let app = Router::new()
         .route("/upload", post(upload_handler))
         .route("/clip", post(clip_handler))
-        .nest_service("/clips", ServeDir::new(&config.output_dir))
+        .route("/debug/state", get(debug_state_handler))
+        .route("/duplicates/:video_id", get(duplicates_handler))
+        .route("/progress/:video_id", get(progress_handler))
+        .route("/encode-progress/:video_id", get(encode_progress_handler))
+        .route("/clips/*path", get(serve_clip_handler))
         .layer(DefaultBodyLimit::max(max_body_size as usize))
         .layer(CorsLayer::permissive())
         .layer(TraceLayer::new_for_http())
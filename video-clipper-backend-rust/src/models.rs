@@ -11,20 +11,143 @@ pub struct VideoMetadata {
     pub original_name: String,
     pub file_size: u64,
     pub uploaded_at: SystemTime,
+    // Populated via ffprobe at upload time; None if probing failed or hasn't run yet
+    #[serde(default)]
+    pub width: Option<u32>,
+    #[serde(default)]
+    pub height: Option<u32>,
+    #[serde(default)]
+    pub codec: Option<String>,
+    #[serde(default)]
+    pub pix_fmt: Option<String>,
+    #[serde(default)]
+    pub container: Option<String>,
+    #[serde(default)]
+    pub frame_count: Option<u64>,
+    #[serde(default)]
+    pub audio_codec: Option<String>,
+    #[serde(default)]
+    pub frame_rate: Option<f64>,
+    #[serde(default)]
+    pub bit_rate: Option<u64>,
+    // Perceptual-hash fingerprint used for duplicate-upload detection, persisted
+    // so the dedup BK-tree can be rebuilt from stored metadata at startup
+    #[serde(default)]
+    pub fingerprint: Option<Vec<u8>>,
 }
 
 #[derive(Clone)]
 pub struct AppState {
     pub videos: Arc<tokio::sync::RwLock<std::collections::HashMap<String, VideoMetadata>>>,
     pub config: crate::config::Config,
+    // Perceptual-hash index over uploaded videos, for duplicate-upload detection
+    pub dedup_tree: Arc<tokio::sync::RwLock<crate::dedup::BkTree>>,
+    // Clips already generated for a given request, keyed by
+    // "{video_id}:{ClipRequest::request_shape_hash()}"
+    // so a re-upload or a repeat /clip call with the *same* parameters can
+    // reuse them, while a repeat call with different parameters re-encodes
+    // instead of getting back a stale, mismatched result.
+    pub clip_cache: Arc<tokio::sync::RwLock<std::collections::HashMap<String, Vec<Clip>>>>,
+    // Persistent write-through store (SQLite) backing `videos`/`clip_cache` so
+    // metadata survives restarts and is shared across multi-process deployments
+    pub db: crate::db::Db,
+    // video_ids currently being clipped (marked by clip_handler), so retention
+    // cleanup never evicts a clip set out from under an in-flight /clip
+    // request; uploads still being written are not tracked here
+    pub in_progress: Arc<tokio::sync::RwLock<std::collections::HashSet<String>>>,
+    // Last time each video's clips were served (cache hit or a /clips/* download),
+    // used by the `least_recently_served` retention policy
+    pub last_served: Arc<tokio::sync::RwLock<std::collections::HashMap<String, SystemTime>>>,
+    // Live clip-generation progress, broadcast to any GET /progress/{video_id}
+    // SSE subscribers; sending is best-effort (ignored if nobody's listening)
+    pub progress_tx: tokio::sync::broadcast::Sender<ClipProgress>,
+    // Live in-flight *encode* progress (percent/fps/speed/eta from ffmpeg's own
+    // `-progress pipe:1`), broadcast to any GET /encode-progress/{video_id}
+    // SSE subscribers - finer-grained than `progress_tx`'s one-event-per-
+    // finished-clip updates
+    pub encode_progress_tx: tokio::sync::broadcast::Sender<EncodeProgress>,
+    // Count of uploads currently running the pipe-based eager-preview path
+    // (see `config::StreamingIngestConfig`), enforced against
+    // `streaming_ingest.max_concurrent` - kept separate from `in_progress`
+    // since it bounds concurrent *ffmpeg children spawned mid-upload*, not
+    // concurrent videos overall.
+    pub streaming_ingest_inflight: Arc<std::sync::atomic::AtomicUsize>,
 }
 
+/// One progress snapshot for an in-flight `generateClips` run, published as
+/// clips finish so a client can follow a long run instead of only seeing the
+/// final result.
+#[derive(Clone, Debug, Serialize)]
+pub struct ClipProgress {
+    pub video_id: String,
+    pub stage: String, // "encoding" | "done"
+    pub clips_done: usize,
+    pub clips_total: usize,
+    #[serde(default)]
+    pub last_clip_index: Option<usize>,
+    #[serde(default)]
+    pub last_clip_elapsed_secs: Option<f64>,
+}
+
+
+/// One live progress snapshot parsed off a single clip's own `ffmpeg
+/// -progress pipe:1` output, published as the encode runs (not just on
+/// completion) so a client can show a percent/fps/speed/eta meter instead of
+/// only clip-boundary events from `ClipProgress`.
+#[derive(Clone, Debug, Serialize)]
+pub struct EncodeProgress {
+    pub video_id: String,
+    pub clip_id: String,
+    pub percent: f64,
+    pub fps: f64,
+    pub speed: f64,
+    pub eta_seconds: f64,
+}
+
+/// Bundles the broadcast channel and identifying fields `ffmpeg::generate_clip`
+/// needs to publish `EncodeProgress` updates for one clip, so the function
+/// doesn't have to take video_id/clip_id/duration/channel as four loose args.
+#[derive(Clone)]
+pub struct EncodeProgressReporter {
+    pub tx: tokio::sync::broadcast::Sender<EncodeProgress>,
+    pub video_id: String,
+    pub clip_id: String,
+    pub total_duration: f64,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ClipMode {
+    /// Walk the timeline in blind `max_length` increments (optionally snapped
+    /// to the nearest scene cut if `scene_detection.enabled` is on).
+    #[default]
+    FixedLength,
+    /// Place cut points on real scene changes first, only falling back to
+    /// `max_length` when a scene runs long.
+    SceneDetect,
+}
 
 #[derive(Deserialize)]
 pub struct ClipRequest {
     pub video_id: String,
     #[serde(default = "default_max_length")]
     pub max_length: f64,
+    #[serde(default)]
+    pub mode: ClipMode,
+    /// Names of `config.output_profiles` presets to render instead of the
+    /// default rendition, e.g. `["hevc_1080p", "av1_720p"]` for an adaptive
+    /// playback ladder. Empty (or only unknown names) means just the single
+    /// default rendition.
+    #[serde(default)]
+    pub output_profiles: Vec<String>,
+    /// Override `config.output_format.container` for just this request -
+    /// `"mp4"` for a single progressive file, or `"hls"` for a streamable
+    /// fragmented-mp4 ladder (see `ffmpeg::mux_hls`) that a client can start
+    /// playing and seek within before the whole clip has downloaded. `None`,
+    /// or any value other than `"mp4"`/`"hls"`, leaves the configured
+    /// default container untouched.
+    #[serde(default)]
+    pub output_container: Option<String>,
 }
 
 fn default_max_length() -> f64 {
@@ -33,17 +156,62 @@ fn default_max_length() -> f64 {
     15.0
 }
 
-#[derive(Serialize)]
+impl ClipRequest {
+    /// Hash of the request-shaping fields (everything but `video_id`, which
+    /// scopes identity separately) - `max_length`/`mode`/`output_profiles`/
+    /// `output_container`. `video_id` alone isn't enough to key a clip cache,
+    /// since two `/clip` calls for the same video with different parameters
+    /// must not reuse each other's clips; a repeat call with identical
+    /// parameters should still hit the cache.
+    pub fn request_shape_hash(&self) -> String {
+        use sha2::{Digest, Sha256};
+
+        let mut hasher = Sha256::new();
+        hasher.update(self.max_length.to_bits().to_le_bytes());
+        hasher.update([self.mode as u8]);
+        for profile in &self.output_profiles {
+            hasher.update(profile.as_bytes());
+            hasher.update([0u8]);
+        }
+        hasher.update(self.output_container.as_deref().unwrap_or("").as_bytes());
+
+        format!("{:x}", hasher.finalize())
+    }
+}
+
+#[derive(Clone, Serialize)]
 pub struct Clip {
     pub id: String,
     pub url: String,
     pub thumbnail_url: String,
     pub duration: f64,
+    #[serde(default)]
+    pub width: Option<u32>,
+    #[serde(default)]
+    pub height: Option<u32>,
+    /// Which `output_profiles` preset produced this rendition; `None` for the
+    /// single default rendition when no profiles were requested.
+    #[serde(default)]
+    pub profile: Option<String>,
+    #[serde(default)]
+    pub codec: Option<String>,
 }
 
 #[derive(Serialize)]
 pub struct UploadResponse {
     pub video_id: String,
+    #[serde(default)]
+    pub width: Option<u32>,
+    #[serde(default)]
+    pub height: Option<u32>,
+    #[serde(default)]
+    pub codec: Option<String>,
+    #[serde(default)]
+    pub audio_codec: Option<String>,
+    #[serde(default)]
+    pub frame_rate: Option<f64>,
+    #[serde(default)]
+    pub bit_rate: Option<u64>,
 }
 
 #[derive(Serialize)]
@@ -51,6 +219,19 @@ pub struct ClipResponse {
     pub clips: Vec<Clip>,
 }
 
+#[derive(Serialize)]
+pub struct DuplicateMatch {
+    pub video_id: String,
+    // Hamming distance between fingerprints, normalized to 0.0 (identical) - 1.0
+    pub distance: f64,
+}
+
+#[derive(Serialize)]
+pub struct DuplicatesResponse {
+    pub video_id: String,
+    pub duplicates: Vec<DuplicateMatch>,
+}
+
 #[derive(Serialize)]
 pub struct ErrorResponse {
     pub error: String,
@@ -60,7 +241,7 @@ pub struct ErrorResponse {
 pub struct ConfigResponse {
     pub max_concurrent_clips: usize,
     pub max_file_size: u64,
-    pub max_concurrent_videos: usize, // Calculated: safe number of videos to process concurrently
+    pub max_concurrent_videos: usize, // Calculated: safe number of videos to process concurrently, adapted to live memory pressure
     pub system_info: SystemInfoResponse,
 }
 
@@ -69,5 +250,6 @@ pub struct SystemInfoResponse {
     pub cpus: usize,
     pub memory_free_gb: f64,
     pub memory_total_gb: f64,
+    pub process_rss_mb: f64,
 }
 
@@ -0,0 +1,172 @@
+use crate::config::PublishConfig;
+use crate::models::Clip;
+use anyhow::{Context, Result};
+use std::path::Path;
+use std::sync::Arc;
+use tokio::sync::Semaphore;
+use tracing::{error, info};
+
+/// A destination clips can be delivered to once `generateClips` finishes.
+/// Implementations upload (or otherwise hand off) a single clip; fanning out
+/// over many clips with bounded concurrency and rate-limiting is handled by
+/// `publish_clips`, not by the sink itself, so new sinks only need this one method.
+pub trait ClipSink {
+    async fn publish(
+        &self,
+        video_id: &str,
+        clip: &Clip,
+        clip_path: &Path,
+        thumbnail_path: &Path,
+    ) -> Result<()>;
+}
+
+/// Uploads a clip to a configured Telegram chat/channel via the Bot API's
+/// `sendVideo` method, with the clip's thumbnail as the cover image, duration
+/// from `Clip.duration`, and a caption templated from the video/clip id.
+#[derive(Clone)]
+pub struct TelegramSink {
+    bot_token: String,
+    chat_id: String,
+    caption_template: String,
+    client: reqwest::Client,
+}
+
+impl TelegramSink {
+    pub fn new(bot_token: String, chat_id: String, caption_template: String) -> Self {
+        Self {
+            bot_token,
+            chat_id,
+            caption_template,
+            client: reqwest::Client::new(),
+        }
+    }
+
+    fn caption(&self, video_id: &str, clip: &Clip) -> String {
+        self.caption_template
+            .replace("{video_id}", video_id)
+            .replace("{clip_id}", &clip.id)
+    }
+}
+
+impl ClipSink for TelegramSink {
+    async fn publish(
+        &self,
+        video_id: &str,
+        clip: &Clip,
+        clip_path: &Path,
+        thumbnail_path: &Path,
+    ) -> Result<()> {
+        let video_bytes = tokio::fs::read(clip_path)
+            .await
+            .with_context(|| format!("Failed to read clip file {}", clip_path.display()))?;
+
+        let mut form = reqwest::multipart::Form::new()
+            .text("chat_id", self.chat_id.clone())
+            .text("duration", (clip.duration.round() as i64).to_string())
+            .text("supports_streaming", "true")
+            .text("caption", self.caption(video_id, clip))
+            .part(
+                "video",
+                reqwest::multipart::Part::bytes(video_bytes)
+                    .file_name(format!("{}.mp4", clip.id))
+                    .mime_str("video/mp4")?,
+            );
+
+        if let Some(w) = clip.width {
+            form = form.text("width", w.to_string());
+        }
+        if let Some(h) = clip.height {
+            form = form.text("height", h.to_string());
+        }
+
+        if let Ok(thumbnail_bytes) = tokio::fs::read(thumbnail_path).await {
+            form = form.part(
+                "thumb",
+                reqwest::multipart::Part::bytes(thumbnail_bytes)
+                    .file_name(format!("{}.jpg", clip.id))
+                    .mime_str("image/jpeg")?,
+            );
+        }
+
+        let url = format!("https://api.telegram.org/bot{}/sendVideo", self.bot_token);
+        let response = self
+            .client
+            .post(&url)
+            .multipart(form)
+            .send()
+            .await
+            .context("Failed to reach Telegram Bot API")?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            anyhow::bail!("Telegram sendVideo failed ({}): {}", status, body);
+        }
+
+        Ok(())
+    }
+}
+
+/// Publish every clip to `sink` concurrently, capped at
+/// `config.max_concurrent_uploads` with a minimum spacing between requests so
+/// a burst of short clips doesn't trip the sink's flood limits. Waits for
+/// every upload, then returns `Err` if any of them failed, so the caller can
+/// hold off on deleting the source video until every clip is confirmed
+/// delivered.
+pub async fn publish_clips<S>(
+    sink: S,
+    config: &PublishConfig,
+    video_id: &str,
+    clips: &[Clip],
+    output_dir: &Path,
+) -> Result<()>
+where
+    S: ClipSink + Clone + Send + Sync + 'static,
+{
+    let semaphore = Arc::new(Semaphore::new(config.max_concurrent_uploads.max(1)));
+    let mut handles = Vec::with_capacity(clips.len());
+
+    for clip in clips {
+        let permit = semaphore.clone().acquire_owned().await?;
+        let sink = sink.clone();
+        let clip = clip.clone();
+        let clip_path = output_dir.join(format!("{}.mp4", clip.id));
+        let thumbnail_path = output_dir.join(format!("{}.jpg", clip.id));
+        let video_id = video_id.to_string();
+        let min_interval_ms = config.min_interval_ms;
+
+        let handle = tokio::spawn(async move {
+            let _permit = permit; // Hold until this upload is done
+            let result = sink.publish(&video_id, &clip, &clip_path, &thumbnail_path).await;
+            tokio::time::sleep(std::time::Duration::from_millis(min_interval_ms)).await;
+            result.map(|_| clip.id.clone())
+        });
+        handles.push(handle);
+    }
+
+    let mut failures = Vec::new();
+    for handle in handles {
+        match handle.await {
+            Ok(Ok(clip_id)) => info!("[publish] ✅ Delivered clip {} to Telegram", clip_id),
+            Ok(Err(e)) => {
+                error!("[publish] ❌ Failed to deliver a clip: {}", e);
+                failures.push(e.to_string());
+            }
+            Err(e) => {
+                error!("[publish] ❌ Upload task panicked: {}", e);
+                failures.push(e.to_string());
+            }
+        }
+    }
+
+    if failures.is_empty() {
+        Ok(())
+    } else {
+        anyhow::bail!(
+            "{} of {} clip uploads failed: {}",
+            failures.len(),
+            clips.len(),
+            failures.join("; ")
+        );
+    }
+}
@@ -0,0 +1,214 @@
+use crate::config::SceneDetectionConfig;
+use anyhow::{Context, Result};
+use std::path::Path;
+use std::process::Stdio;
+use tokio::process::Command;
+
+/// Frames sampled per second of video for the analysis pass. Coarser than
+/// real playback framerate on purpose - a shot rarely changes inside a
+/// fraction of a second, and sampling this sparsely keeps a full-length pass
+/// cheap with no external dependency beyond ffmpeg itself.
+const STANDARD_SAMPLE_FPS: f64 = 2.0;
+const FAST_SAMPLE_FPS: f64 = 1.0;
+/// Side length, in pixels, of the analysis grid: each frame is downscaled to
+/// GRID_SIDE * GRID_SIDE grayscale pixels, which `frame_features` then pools
+/// into 8x8-pixel blocks. Aspect ratio is ignored since only relative
+/// frame-to-frame change is measured, not the image itself.
+const STANDARD_GRID_SIDE: u32 = 64;
+const FAST_GRID_SIDE: u32 = 32;
+/// Number of preceding frame-to-frame diffs averaged to set the adaptive
+/// per-frame cut threshold (mean + sensitivity * stddev)
+const DIFF_WINDOW: usize = 30;
+
+/// Detect scene cuts by decoding the source as a sequence of small grayscale
+/// frames (no dependency beyond ffmpeg's `rawvideo` muxer), turning each into
+/// a feature vector (overall mean luminance + one mean per 8x8 block), and
+/// flagging a cut wherever the sum of absolute differences between
+/// consecutive frames' feature vectors clears an adaptive threshold - the
+/// mean plus `sensitivity` standard deviations of the last `DIFF_WINDOW`
+/// diffs, floored at `config.threshold` so a mostly-static video's tiny
+/// rolling stddev doesn't get noise flagged as cuts. Returns a sorted list of
+/// cut timestamps (seconds from the start of the video), with any cuts closer
+/// together than `min_scene_len` merged away.
+pub async fn detect_scene_cuts(path: &Path, config: &SceneDetectionConfig) -> Result<Vec<f64>> {
+    let fast = config.method == "fast";
+    let fps = if fast { FAST_SAMPLE_FPS } else { STANDARD_SAMPLE_FPS };
+    let grid_side = config
+        .downscale_height
+        .unwrap_or(if fast { FAST_GRID_SIDE } else { STANDARD_GRID_SIDE });
+
+    let frames = decode_analysis_frames(path, fps, grid_side).await?;
+    if frames.len() < 2 {
+        return Ok(Vec::new());
+    }
+
+    let features: Vec<Vec<f64>> = frames.iter().map(|f| frame_features(f, grid_side)).collect();
+    let diffs: Vec<f64> = features
+        .windows(2)
+        .map(|w| sum_abs_diff(&w[0], &w[1]))
+        .collect();
+
+    let mut cuts = Vec::new();
+    let mut window: Vec<f64> = Vec::with_capacity(DIFF_WINDOW);
+    for (i, &diff) in diffs.iter().enumerate() {
+        if window.len() >= 2 {
+            let mean = window.iter().sum::<f64>() / window.len() as f64;
+            let variance = window.iter().map(|d| (d - mean).powi(2)).sum::<f64>() / window.len() as f64;
+            let stddev = variance.sqrt();
+            let adaptive = (mean + config.sensitivity * stddev).max(config.threshold);
+            if diff > adaptive {
+                // Diff i is between sampled frame i and i+1 - the cut itself
+                // lands on the later frame, where the new shot actually starts
+                cuts.push((i + 1) as f64 / fps);
+            }
+        }
+
+        window.push(diff);
+        if window.len() > DIFF_WINDOW {
+            window.remove(0);
+        }
+    }
+
+    Ok(merge_close_cuts(cuts, config.min_scene_len))
+}
+
+/// Decode the whole video as a sequence of `grid_side x grid_side` grayscale
+/// frames sampled at `fps`, one `Vec<u8>` of raw pixels per frame.
+async fn decode_analysis_frames(path: &Path, fps: f64, grid_side: u32) -> Result<Vec<Vec<u8>>> {
+    let output = Command::new("ffmpeg")
+        .arg("-i").arg(path)
+        .arg("-vf").arg(format!(
+            "fps={},scale={}:{}:flags=area,format=gray",
+            fps, grid_side, grid_side
+        ))
+        .arg("-f").arg("rawvideo")
+        .arg("-")
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .output()
+        .await
+        .context("Failed to execute ffmpeg for scene-detection frame decode")?;
+
+    let frame_size = (grid_side * grid_side) as usize;
+    Ok(output
+        .stdout
+        .chunks_exact(frame_size)
+        .map(|chunk| chunk.to_vec())
+        .collect())
+}
+
+/// Turn a raw grayscale frame into a feature vector: the overall mean
+/// luminance followed by one mean per 8x8 block (in raster order), so two
+/// frames that differ in one region but not others contribute a proportional
+/// share of the total diff rather than washing out into a single average.
+fn frame_features(frame: &[u8], grid_side: u32) -> Vec<f64> {
+    const BLOCK: u32 = 8;
+    let blocks_per_side = grid_side / BLOCK;
+    let mut features = Vec::with_capacity(1 + (blocks_per_side * blocks_per_side) as usize);
+
+    let overall_mean = frame.iter().map(|&p| p as f64).sum::<f64>() / frame.len() as f64;
+    features.push(overall_mean);
+
+    for block_row in 0..blocks_per_side {
+        for block_col in 0..blocks_per_side {
+            let mut sum = 0.0;
+            for y in 0..BLOCK {
+                for x in 0..BLOCK {
+                    let px = block_col * BLOCK + x;
+                    let py = block_row * BLOCK + y;
+                    sum += frame[(py * grid_side + px) as usize] as f64;
+                }
+            }
+            features.push(sum / (BLOCK * BLOCK) as f64);
+        }
+    }
+
+    features
+}
+
+fn sum_abs_diff(a: &[f64], b: &[f64]) -> f64 {
+    a.iter().zip(b.iter()).map(|(x, y)| (x - y).abs()).sum()
+}
+
+/// Collapse cuts that fall within `min_scene_len` seconds of the previous
+/// kept cut so scenes never get sliced into fragments shorter than that.
+fn merge_close_cuts(cuts: Vec<f64>, min_scene_len: f64) -> Vec<f64> {
+    let mut merged: Vec<f64> = Vec::with_capacity(cuts.len());
+    for cut in cuts {
+        match merged.last() {
+            Some(&last) if cut - last < min_scene_len => continue,
+            _ => merged.push(cut),
+        }
+    }
+    merged
+}
+
+/// Given a desired clip boundary `target`, return the detected cut nearest to
+/// it, as long as it's within `snap_window` seconds - otherwise fall back to
+/// the target itself so we never distort clip length wildly chasing a scene
+/// cut that isn't actually nearby.
+pub fn snap_to_nearest_cut(target: f64, cuts: &[f64], snap_window: f64) -> f64 {
+    cuts.iter()
+        .map(|&cut| (cut, (cut - target).abs()))
+        .filter(|&(_, dist)| dist <= snap_window)
+        .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal))
+        .map(|(cut, _)| cut)
+        .unwrap_or(target)
+}
+
+/// Build clip segments greedily from detected scene cuts: a new clip starts at
+/// each cut, but a split is forced once the accumulated length would exceed
+/// `max_length`, and any segment shorter than `min_clip_duration` is merged
+/// into its neighbor rather than shipped as its own clip. Falls back to plain
+/// `max_length` slicing wherever no cuts fall inside the remaining duration.
+pub fn build_scene_aware_segments(
+    cuts: &[f64],
+    duration: f64,
+    max_length: f64,
+    min_clip_duration: f64,
+) -> Vec<(f64, f64)> {
+    let mut boundaries: Vec<f64> = vec![0.0];
+    let mut cursor = 0.0;
+
+    while cursor < duration {
+        let next_cut = cuts
+            .iter()
+            .copied()
+            .find(|&c| c > cursor && c - cursor <= max_length);
+
+        let next_boundary = match next_cut {
+            Some(c) if c < duration => c,
+            _ => (cursor + max_length).min(duration),
+        };
+
+        boundaries.push(next_boundary);
+        cursor = next_boundary;
+    }
+
+    // Merge any too-short trailing segment into its predecessor.
+    let mut merged: Vec<f64> = Vec::with_capacity(boundaries.len());
+    for &b in &boundaries {
+        merged.push(b);
+        while merged.len() >= 3 {
+            let last = merged[merged.len() - 1];
+            let prev = merged[merged.len() - 2];
+            if last - prev < min_clip_duration {
+                merged.remove(merged.len() - 2);
+            } else {
+                break;
+            }
+        }
+    }
+
+    // Merge a too-short leading segment into the one after it.
+    if merged.len() >= 3 && merged[1] - merged[0] < min_clip_duration {
+        merged.remove(1);
+    }
+
+    merged
+        .windows(2)
+        .map(|w| (w[0], w[1] - w[0]))
+        .filter(|&(_, dur)| dur > 0.0)
+        .collect()
+}
@@ -71,14 +71,87 @@ pub fn get_system_info() -> SystemInfo {
     }
 }
 
+/// Detected CPU parallelism. Prefers `std::thread::available_parallelism`
+/// over the `num_cpus` crate since it respects container cgroup CPU quotas
+/// (num_cpus reads the host's physical core count regardless of cgroup limits).
+pub fn available_parallelism() -> usize {
+    std::thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(1)
+}
+
+/// Rough memory budget assumed per concurrently-running ffmpeg encode, used
+/// to size `max_concurrent_clips` so it doesn't outrun free RAM on small boxes
+const MEM_BUDGET_GB_PER_CLIP: f64 = 0.5;
+
+/// Default `max_concurrent_clips`: CPU-bound (leave one core free for the
+/// async runtime/OS, capped between 2 and 8) and also memory-bound against
+/// currently free RAM, so a busy or small-memory host doesn't default to more
+/// concurrent encodes than it can actually hold in memory.
+pub fn recommended_concurrency() -> usize {
+    let cpu_count = available_parallelism();
+    let cpu_bound = (cpu_count.saturating_sub(1)).max(2).min(8);
+
+    let mem_free_gb = get_system_info().memory_free_gb;
+    let mem_bound = ((mem_free_gb / MEM_BUDGET_GB_PER_CLIP).floor() as usize).max(1);
+
+    cpu_bound.min(mem_bound).max(1)
+}
+
 pub fn get_memory_usage() -> MemoryUsage {
-    // Rust doesn't have the same memory tracking as Node.js
-    // We'll use a simplified version
-    // In production, you might want to use a crate like `memory-stats`
     MemoryUsage {
-        rss_mb: 0.0, // Placeholder - would need external crate for accurate measurement
-        heap_mb: 0.0,
+        rss_mb: process_rss_mb(),
+        heap_mb: 0.0, // Rust doesn't expose a separate heap figure the way Node.js does
+    }
+}
+
+/// Sample this process's resident set size directly from the kernel.
+/// Linux-only for now (the only platform this backend is deployed on);
+/// falls back to 0.0 elsewhere so callers don't have to special-case it.
+#[cfg(target_os = "linux")]
+fn process_rss_mb() -> f64 {
+    // /proc/self/statm: size resident shared text lib data dt (all in pages)
+    let statm = match std::fs::read_to_string("/proc/self/statm") {
+        Ok(s) => s,
+        Err(_) => return 0.0,
+    };
+
+    let resident_pages: u64 = match statm.split_whitespace().nth(1).and_then(|v| v.parse().ok()) {
+        Some(p) => p,
+        None => return 0.0,
+    };
+
+    let page_size_bytes = 4096.0; // standard x86_64/aarch64 Linux page size
+    (resident_pages as f64 * page_size_bytes) / 1024.0 / 1024.0
+}
+
+#[cfg(not(target_os = "linux"))]
+fn process_rss_mb() -> f64 {
+    0.0
+}
+
+/// Admission-control check combining live system free memory and this
+/// process's own RSS against the configured high-water marks. Called before
+/// accepting new uploads/clip requests so concurrency adapts to real memory
+/// pressure instead of only the static `max_concurrent_clips` limit.
+pub fn check_admission(config: &Config) -> Result<(), String> {
+    let sys_info = get_system_info();
+    if sys_info.memory_free_gb < config.admission.min_free_memory_gb {
+        return Err(format!(
+            "server busy: only {:.2} GB free memory (minimum {:.2} GB required), retry later",
+            sys_info.memory_free_gb, config.admission.min_free_memory_gb
+        ));
     }
+
+    let rss_mb = process_rss_mb();
+    if rss_mb > config.admission.max_process_rss_mb {
+        return Err(format!(
+            "server busy: process RSS {:.0} MB exceeds high-water mark {:.0} MB, retry later",
+            rss_mb, config.admission.max_process_rss_mb
+        ));
+    }
+
+    Ok(())
 }
 
 pub fn print_startup_info(config: &Config) {
@@ -0,0 +1,314 @@
+use crate::config::{AdaptiveQualityConfig, TargetQualityConfig};
+use anyhow::{Context, Result};
+use std::collections::HashMap;
+use std::path::Path;
+use std::process::Stdio;
+use std::sync::Mutex;
+use tokio::process::Command;
+use tracing::warn;
+
+/// Encode a short probe of `duration` seconds starting at `start_time` using
+/// the given CRF, then score it against the same segment of the source with
+/// ffmpeg's `libvmaf` filter. Returns the mean VMAF score.
+async fn probe_vmaf(
+    input_path: &Path,
+    start_time: f64,
+    duration: f64,
+    crf: u8,
+    preset: &str,
+) -> Result<f64> {
+    let probe_dir = std::env::temp_dir();
+    let probe_id = uuid::Uuid::new_v4().to_string();
+    let probe_path = probe_dir.join(format!("vmaf-probe-{}.mp4", probe_id));
+    let log_path = probe_dir.join(format!("vmaf-log-{}.json", probe_id));
+
+    let encode_output = Command::new("ffmpeg")
+        .arg("-ss").arg(start_time.to_string())
+        .arg("-i").arg(input_path)
+        .arg("-t").arg(duration.to_string())
+        .arg("-c:v").arg("libx264")
+        .arg("-preset").arg(preset)
+        .arg("-crf").arg(crf.to_string())
+        .arg("-an")
+        .arg("-y").arg(&probe_path)
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .output()
+        .await
+        .context("Failed to execute ffmpeg for VMAF probe encode")?;
+
+    if !encode_output.status.success() {
+        let stderr = String::from_utf8_lossy(&encode_output.stderr);
+        anyhow::bail!("ffmpeg probe encode failed at crf={}: {}", crf, stderr);
+    }
+
+    // Score the probe against the same segment of the source. libvmaf needs
+    // identical dimensions and fps; the probe keeps the source's own, and the
+    // scale=-1:-1 steps leave both inputs at their own size.
+    let filter = format!(
+        "[0:v]scale=-1:-1:flags=bicubic[dist];[1:v]scale=-1:-1:flags=bicubic[ref];[dist][ref]libvmaf=log_fmt=json:log_path={}",
+        log_path.display()
+    );
+
+    let vmaf_output = Command::new("ffmpeg")
+        .arg("-i").arg(&probe_path)
+        .arg("-ss").arg(start_time.to_string())
+        .arg("-i").arg(input_path)
+        .arg("-t").arg(duration.to_string())
+        .arg("-lavfi").arg(&filter)
+        .arg("-f").arg("null")
+        .arg("-")
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .output()
+        .await
+        .context("Failed to execute ffmpeg for VMAF scoring")?;
+
+    let _ = tokio::fs::remove_file(&probe_path).await;
+
+    if !vmaf_output.status.success() {
+        let stderr = String::from_utf8_lossy(&vmaf_output.stderr);
+        let _ = tokio::fs::remove_file(&log_path).await;
+        anyhow::bail!("ffmpeg libvmaf scoring failed at crf={}: {}", crf, stderr);
+    }
+
+    let score = parse_vmaf_score(&log_path).await;
+    let _ = tokio::fs::remove_file(&log_path).await;
+    score
+}
+
+async fn parse_vmaf_score(log_path: &Path) -> Result<f64> {
+    let content = tokio::fs::read_to_string(log_path)
+        .await
+        .context("Failed to read libvmaf log")?;
+    let parsed: serde_json::Value =
+        serde_json::from_str(&content).context("Failed to parse libvmaf json log")?;
+    parsed["pooled_metrics"]["vmaf"]["mean"]
+        .as_f64()
+        .context("libvmaf log missing pooled_metrics.vmaf.mean")
+}
+
+/// Binary-search over [min_crf, max_crf] for the CRF whose probe VMAF is
+/// closest to `target.target_vmaf`. VMAF decreases monotonically as CRF
+/// rises, so each sample narrows the bracket; the next guess is the
+/// bracket's midpoint (no interpolation between samples). Gives up after
+/// `target.probes` samples and returns the closest one seen.
+pub async fn solve_crf(
+    input_path: &Path,
+    start_time: f64,
+    target: &TargetQualityConfig,
+    preset: &str,
+) -> Result<u8> {
+    const TOLERANCE: f64 = 0.5;
+
+    let mut samples: Vec<(u8, f64)> = Vec::with_capacity(target.probes);
+    let mut low = target.min_crf;
+    let mut high = target.max_crf;
+
+    // First sample: midpoint of the allowed range.
+    let mut next_crf = low + (high - low) / 2;
+
+    for _ in 0..target.probes.max(1) {
+        let vmaf = probe_vmaf(input_path, start_time, target.probe_duration, next_crf, preset).await?;
+        samples.push((next_crf, vmaf));
+
+        if (vmaf - target.target_vmaf).abs() <= TOLERANCE {
+            break;
+        }
+
+        // VMAF decreases as CRF increases; quality too low -> lower CRF (higher quality).
+        if vmaf < target.target_vmaf {
+            high = next_crf.saturating_sub(1).max(low);
+        } else {
+            low = next_crf.saturating_add(1).min(high);
+        }
+
+        if low >= high {
+            break;
+        }
+        next_crf = low + (high - low) / 2;
+
+        if samples.iter().any(|(crf, _)| *crf == next_crf) {
+            break;
+        }
+    }
+
+    let best = samples
+        .iter()
+        .min_by(|a, b| {
+            (a.1 - target.target_vmaf)
+                .abs()
+                .partial_cmp(&(b.1 - target.target_vmaf).abs())
+                .unwrap_or(std::cmp::Ordering::Equal)
+        })
+        .map(|(crf, _)| *crf)
+        .unwrap_or(next_crf);
+
+    Ok(best)
+}
+
+/// Whether the local ffmpeg build has `libvmaf` compiled in. Cached after
+/// first check, same pattern as `ffmpeg::detect_hardware_codec`.
+static LIBVMAF_AVAILABLE: Mutex<Option<bool>> = Mutex::new(None);
+
+async fn is_libvmaf_available() -> bool {
+    {
+        let cache = LIBVMAF_AVAILABLE.lock().unwrap();
+        if let Some(available) = *cache {
+            return available;
+        }
+    }
+
+    let available = Command::new("ffmpeg")
+        .arg("-hide_banner")
+        .arg("-filters")
+        .output()
+        .await
+        .map(|output| String::from_utf8_lossy(&output.stdout).contains("libvmaf"))
+        .unwrap_or(false);
+
+    let mut cache = LIBVMAF_AVAILABLE.lock().unwrap();
+    *cache = Some(available);
+    available
+}
+
+/// Encode the first `probe_frames` consecutive frames of the segment,
+/// downscaled to 640 wide, at `crf`, score them against the matching source
+/// frames, and return the mean VMAF. No frames are skipped between samples.
+/// Cheaper per-sample than `probe_vmaf` above since it bounds the probe by
+/// frame count instead of wall-clock duration, and reads the score
+/// straight off ffmpeg's own "VMAF score: x" stderr line instead of a JSON
+/// log file.
+async fn probe_vmaf_frames(
+    input_path: &Path,
+    start_time: f64,
+    crf: u8,
+    preset: &str,
+    probe_frames: usize,
+) -> Result<f64> {
+    let probe_path = std::env::temp_dir().join(format!("vmaf-adaptive-probe-{}.mp4", uuid::Uuid::new_v4()));
+
+    let encode_output = Command::new("ffmpeg")
+        .arg("-ss").arg(start_time.to_string())
+        .arg("-i").arg(input_path)
+        .arg("-frames:v").arg(probe_frames.to_string())
+        .arg("-vf").arg("scale=640:-2")
+        .arg("-c:v").arg("libx264")
+        .arg("-preset").arg(preset)
+        .arg("-crf").arg(crf.to_string())
+        .arg("-an")
+        .arg("-y").arg(&probe_path)
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .output()
+        .await
+        .context("Failed to execute ffmpeg for adaptive-quality probe encode")?;
+
+    if !encode_output.status.success() {
+        let stderr = String::from_utf8_lossy(&encode_output.stderr);
+        anyhow::bail!("ffmpeg adaptive-quality probe encode failed at crf={}: {}", crf, stderr);
+    }
+
+    let filter = "[0:v]scale=640:-2[dist];[1:v]scale=640:-2,fps=fps=30[ref];[dist][ref]libvmaf";
+    let score_output = Command::new("ffmpeg")
+        .arg("-i").arg(&probe_path)
+        .arg("-ss").arg(start_time.to_string())
+        .arg("-i").arg(input_path)
+        .arg("-frames:v").arg(probe_frames.to_string())
+        .arg("-lavfi").arg(filter)
+        .arg("-f").arg("null")
+        .arg("-")
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .output()
+        .await
+        .context("Failed to execute ffmpeg for adaptive-quality scoring")?;
+
+    let _ = tokio::fs::remove_file(&probe_path).await;
+
+    let stderr = String::from_utf8_lossy(&score_output.stderr);
+    if !score_output.status.success() {
+        anyhow::bail!("ffmpeg libvmaf scoring failed at crf={}: {}", crf, stderr);
+    }
+
+    stderr
+        .lines()
+        .find_map(|line| line.split_once("VMAF score: ").map(|(_, v)| v))
+        .and_then(|v| v.trim().parse::<f64>().ok())
+        .context("libvmaf stderr missing \"VMAF score:\" line")
+}
+
+/// Bounded binary search over `[config.crf_min, config.crf_max]` for the CRF
+/// whose probe VMAF is closest to `config.vmaf`, memoizing every CRF probed
+/// so a bracket that revisits one never re-encodes it. Raises CRF (smaller
+/// file) when a probe measures above `config.vmaf + config.tolerance`, lowers
+/// it when below, and stops as soon as a probe lands within tolerance or the
+/// search interval collapses to a single value.
+///
+/// Skips the search entirely and returns an error (so callers fall back to
+/// their statically configured CRF, same as any other probe failure here) if
+/// `libvmaf` isn't compiled into the local ffmpeg - logging a warning first,
+/// the same way `ffmpeg::detect_hardware_codec` does for a missing encoder.
+pub async fn solve_crf_adaptive(
+    input_path: &Path,
+    start_time: f64,
+    config: &AdaptiveQualityConfig,
+    preset: &str,
+) -> Result<u8> {
+    if !is_libvmaf_available().await {
+        warn!("[vmaf] ⚠️  libvmaf not available, skipping adaptive-quality CRF search");
+        anyhow::bail!("libvmaf not available");
+    }
+
+    let mut cache: HashMap<u8, f64> = HashMap::new();
+    let mut low = config.crf_min;
+    let mut high = config.crf_max;
+    let mut last_crf = low + (high - low) / 2;
+
+    loop {
+        if low > high {
+            break;
+        }
+        let crf = low + (high - low) / 2;
+        let vmaf = match cache.get(&crf) {
+            Some(&v) => v,
+            None => {
+                let v = probe_vmaf_frames(input_path, start_time, crf, preset, config.probe_frames).await?;
+                cache.insert(crf, v);
+                v
+            }
+        };
+        last_crf = crf;
+
+        if (vmaf - config.vmaf).abs() <= config.tolerance {
+            break;
+        }
+
+        if vmaf > config.vmaf + config.tolerance {
+            // Quality to spare - raise CRF for a smaller file.
+            if crf >= high {
+                break;
+            }
+            low = crf + 1;
+        } else {
+            // Below target - lower CRF for higher quality.
+            if crf <= low {
+                break;
+            }
+            high = crf - 1;
+        }
+    }
+
+    let best_crf = cache
+        .iter()
+        .min_by(|a, b| {
+            (a.1 - config.vmaf)
+                .abs()
+                .partial_cmp(&(b.1 - config.vmaf).abs())
+                .unwrap_or(std::cmp::Ordering::Equal)
+        })
+        .map(|(crf, _)| *crf)
+        .unwrap_or(last_crf);
+
+    Ok(best_crf.clamp(config.crf_min, config.crf_max))
+}